@@ -1,3 +1,7 @@
 fn main() {
-    embed_resource::compile("app.rc", embed_resource::NONE);
-}
\ No newline at end of file
+    // 图标资源 (.rc) 只有 Windows 目标需要嵌入；build.rs 运行在宿主机上，
+    // 所以要看目标平台而不是 cfg!(windows)，这样交叉编译也能正确处理
+    if std::env::var("CARGO_CFG_TARGET_OS").as_deref() == Ok("windows") {
+        embed_resource::compile("app.rc", embed_resource::NONE);
+    }
+}
@@ -0,0 +1,124 @@
+//! Animated GIF, APNG and animated WebP.
+//!
+//! All frames are decoded up front on the loader thread, bounded by the
+//! configured frame and memory caps, and played back on the UI thread by an
+//! [`AnimationClock`], which also tells the refresh timer whether the
+//! animation has played through at least once.
+
+use std::{fs::File, io::BufReader, path::Path, time::Duration};
+
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    AnimationDecoder, Delay, Frames, ImageFormat, RgbaImage,
+};
+use serde::{Deserialize, Serialize};
+
+/// Delay used for frames that ask for (almost) none, as browsers do: many
+/// GIFs in the wild store 0 and expect to be shown at about 10 fps.
+pub const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// Playback settings (`animation` in the config).
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct AnimationSettings {
+    /// Play animations; when off only the first frame is shown.
+    pub enabled: bool,
+    /// Frames after this many are dropped and the animation loops early.
+    pub max_frames: usize,
+    /// Upper bound for all decoded frames of one image, in MiB.
+    pub max_memory_mb: usize,
+    /// Hold the refresh timer until the animation has played once.
+    pub wait_for_loop: bool,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        Self { enabled: true, max_frames: 500, max_memory_mb: 256, wait_for_loop: true }
+    }
+}
+
+pub struct AnimationFrame {
+    pub image: RgbaImage,
+    pub delay: Duration,
+}
+
+/// Decodes every frame of an animated image. `None` when the format cannot
+/// be animated, the file is a plain still (e.g. a PNG without `acTL`) or has
+/// a single frame, so the caller decodes it as a still instead.
+pub fn decode_frames(path: &Path, format: ImageFormat, settings: &AnimationSettings) -> Option<Vec<AnimationFrame>> {
+    let reader = BufReader::new(File::open(path).ok()?);
+    let frames = match format {
+        ImageFormat::Gif => GifDecoder::new(reader).ok()?.into_frames(),
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(reader).ok()?;
+            if !decoder.is_apng() { return None; }
+            decoder.apng().into_frames()
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(reader).ok()?;
+            if !decoder.has_animation() { return None; }
+            decoder.into_frames()
+        }
+        _ => return None,
+    };
+    let frames = collect_frames(frames, settings);
+    (frames.len() > 1).then_some(frames)
+}
+
+fn collect_frames(frames: Frames<'_>, settings: &AnimationSettings) -> Vec<AnimationFrame> {
+    let budget = settings.max_memory_mb.saturating_mul(1024 * 1024);
+    let mut used = 0;
+    let mut collected = Vec::new();
+    for frame in frames.take(settings.max_frames.max(1)) {
+        // 中途损坏的文件：已解码的帧照常播放
+        let Ok(frame) = frame else { break };
+        let delay = frame_delay(frame.delay());
+        let image = frame.into_buffer();
+        used += image.as_raw().len();
+        if used > budget && !collected.is_empty() { break; }
+        collected.push(AnimationFrame { image, delay });
+    }
+    collected
+}
+
+fn frame_delay(delay: Delay) -> Duration {
+    let delay = Duration::from(delay);
+    if delay <= Duration::from_millis(10) { DEFAULT_FRAME_DELAY } else { delay }
+}
+
+/// Maps the time since an animation started to the frame to show.
+#[derive(Clone, Debug)]
+pub struct AnimationClock {
+    delays: Vec<Duration>,
+    loop_duration: Duration,
+}
+
+impl AnimationClock {
+    pub fn new(delays: Vec<Duration>) -> Self {
+        let loop_duration = delays.iter().sum();
+        Self { delays, loop_duration }
+    }
+
+    pub fn loop_duration(&self) -> Duration { self.loop_duration }
+
+    /// Frame index and number of completed loops after `elapsed`.
+    pub fn frame_at(&self, elapsed: Duration) -> (usize, u32) {
+        self.locate(elapsed).map_or((0, 0), |(frame, loops, _)| (frame, loops))
+    }
+
+    /// Time from `elapsed` until the next frame is due.
+    pub fn until_next_frame(&self, elapsed: Duration) -> Option<Duration> {
+        self.locate(elapsed).map(|(_, _, remaining)| remaining)
+    }
+
+    fn locate(&self, elapsed: Duration) -> Option<(usize, u32, Duration)> {
+        if self.loop_duration.is_zero() { return None; }
+        let loops = (elapsed.as_nanos() / self.loop_duration.as_nanos()) as u32;
+        let mut offset = Duration::from_nanos((elapsed.as_nanos() % self.loop_duration.as_nanos()) as u64);
+        for (frame, delay) in self.delays.iter().enumerate() {
+            if offset < *delay { return Some((frame, loops, *delay - offset)); }
+            offset -= *delay;
+        }
+        None
+    }
+}
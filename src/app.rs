@@ -0,0 +1,1176 @@
+//! The eframe application: the photo view and the settings panel.
+
+use std::{
+    collections::HashMap,
+    fs,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use eframe::{
+    egui::{self, style::Visuals, FontData, FontDefinitions, FontFamily, Frame, Sense},
+    epaint::{pos2, Color32, Pos2, Rect, TextureHandle, Vec2},
+};
+use rfd::FileDialog;
+
+use crate::{
+    animation::AnimationClock,
+    cache::ImageCache,
+    caption::{CaptionInfo, CaptionPosition, CaptionVisibility, PLACEHOLDERS},
+    decoder::{self, DecodePool, RequestId},
+    duplicates::{Admission, Duplicates},
+    exif_data::ExifData,
+    config::{save_config, AppConfig, FolderEntry, FitMode, ImageOrientationFilter, LetterboxFill, PlaybackOrder, ResampleFilter, ResizeAnchor, TimeUnit, WatchMode},
+    index::{self, ImageIndex, IndexEntry, INDEX_FILE},
+    formats::FormatFilter,
+    history::{History, HISTORY_FILE},
+    layout,
+    library::{self, ScanFilter},
+    memories::{DateFilter, DateSelection, Day},
+    platform,
+    playlist::{Playlist, SortKey},
+    ken_burns::{KenBurnsMove, KenBurnsPath},
+    ratings::{ImageRating, Ratings, MAX_STARS, RATINGS_FILE},
+    render::{DecodeOptions, DecodedImage},
+    scanner::{ScanProgress, Scanner},
+    transition::{Layer, SlideDirection, TransitionKind},
+    watcher::{FolderWatcher, LibraryChange},
+};
+
+#[derive(Clone, Debug)]
+pub enum TrayMessage {
+    ShowSettings,
+    Previous,
+    Next,
+    Quit,
+    FocusWindow,
+    ToggleFavorite,
+    Rate(u8),
+    HideCurrent,
+    SwitchAlbum(String),
+}
+
+/// Called with all album names and the active one whenever they change,
+/// so the tray menu can list them.
+pub type AlbumListener = Box<dyn FnMut(&[String], &str)>;
+
+/// The picture on screen: one texture per animation frame.
+#[derive(Clone)]
+struct ShownImage {
+    frames: Vec<TextureHandle>,
+    clock: AnimationClock,
+    shown_at: Instant,
+    // 解码时缩小到的目标尺寸 (物理像素)；原始分辨率时为 None
+    downscaled_to: Option<Vec2>,
+    // 每次显示时重新生成的 Ken Burns 移动路径
+    motion: Option<KenBurnsMove>,
+    // Letterbox 模式下填充两侧的模糊背景
+    background: Option<TextureHandle>,
+    // Cover 模式裁剪的中心 (纹理坐标)，显示时从索引读取
+    focus: Option<Pos2>,
+    // 解码线程顺便读出的 EXIF，标题用，避免在界面线程读文件
+    exif: Option<ExifData>,
+}
+
+impl ShownImage {
+    fn upload(ctx: &egui::Context, name: &str, image: DecodedImage) -> Self {
+        let frames = image.frames.into_iter().enumerate()
+            .map(|(i, frame)| ctx.load_texture(format!("{name}#{i}"), frame, Default::default()))
+            .collect();
+        let background = image.background.map(|background| ctx.load_texture(format!("{name}#background"), background, Default::default()));
+        Self { frames, clock: AnimationClock::new(image.delays), shown_at: Instant::now(), downscaled_to: image.downscaled_to, motion: None, background, focus: None, exif: image.exif }
+    }
+
+    /// A copy for showing again, with the animation started over.
+    fn restarted(&self) -> Self {
+        Self { shown_at: Instant::now(), ..self.clone() }
+    }
+
+    fn byte_size(&self) -> usize {
+        self.frames.iter().chain(&self.background).map(TextureHandle::byte_size).sum()
+    }
+
+    /// The frame due now.
+    fn texture(&self) -> &TextureHandle {
+        &self.frames[self.clock.frame_at(self.shown_at.elapsed()).0]
+    }
+
+    /// Stills count as played through immediately.
+    fn has_looped(&self) -> bool {
+        self.frames.len() < 2 || self.clock.frame_at(self.shown_at.elapsed()).1 > 0
+    }
+
+    fn until_next_frame(&self) -> Option<Duration> {
+        self.clock.until_next_frame(self.shown_at.elapsed())
+    }
+}
+
+/// What a layer is painted with.
+#[derive(Clone, Copy)]
+enum Fill<'a> {
+    Texture(&'a TextureHandle),
+    Color(Color32),
+}
+
+/// The image being replaced, kept on screen until the transition is over.
+struct ActiveTransition {
+    outgoing: ShownImage,
+    started: Instant,
+}
+
+pub struct PhotoWidget {
+    config: AppConfig,
+    index: Arc<Mutex<ImageIndex>>,
+    scanner: Option<Scanner>,
+    scan_progress: ScanProgress,
+    watcher: Option<FolderWatcher>,
+    // 扫描开始后，第一批图片到达时立即显示一张新图片
+    show_first_found: bool,
+    // 设置面板中以逗号分隔编辑的扩展名列表
+    include_extensions_text: String,
+    exclude_extensions_text: String,
+    // 每个文件夹的包含/排除模式，每行一个
+    pattern_texts: HashMap<PathBuf, [String; 2]>,
+    playlist: Playlist,
+    history: History,
+    ratings: Ratings,
+    duplicates: Duplicates,
+    show_duplicate_report: bool,
+    // 滚轮的累计滚动量，超过一格才翻页
+    scroll_accumulator: f32,
+    current_image: Option<ShownImage>,
+    transition: Option<ActiveTransition>,
+    // 切换了图片 (而不是重新解码同一张) 时，新图片出现时播放过渡动画
+    transition_pending: bool,
+    // 正在拖动图片调整裁剪位置
+    adjusting_crop: bool,
+    // 当前图片的标题占位符的值，换图时读取一次 EXIF
+    caption: Option<CaptionInfo>,
+    // 最近显示过的和预取的图片
+    cache: ImageCache<ShownImage>,
+    decoder: DecodePool,
+    // 正在后台解码的图片及其请求，避免重复解码
+    decoding: HashMap<PathBuf, RequestId>,
+    // 当前图片的解码请求；只有它的结果会被显示
+    current_request: Option<RequestId>,
+    last_update: Instant,
+    show_settings: bool,
+    current_image_path: Option<PathBuf>,
+    tray_rx: Receiver<TrayMessage>,
+    on_albums_changed: AlbumListener,
+    // 设置面板中新建或重命名相册时输入的名称
+    album_name_text: String,
+    folder_rx: Receiver<PathBuf>,
+    folder_tx: Sender<PathBuf>,
+    last_window_size: Option<Vec2>,
+    show_drag_bar: bool,
+    hover_leave_time: Option<Instant>,
+    last_screen_check: Instant,
+    pixels_per_point: f32,
+}
+
+impl PhotoWidget {
+    // --- 修改：让 new 函数接收一个已加载的 config ---
+    pub fn new(_cc: &eframe::CreationContext<'_>, tray_rx: Receiver<TrayMessage>, config: AppConfig, on_albums_changed: AlbumListener) -> Self {
+        let mut config = config;
+        config.sync_refresh_unit();
+        let (folder_tx, folder_rx) = mpsc::channel();
+        let include_extensions_text = config.formats.include_extensions.join(", ");
+        let exclude_extensions_text = config.formats.exclude_extensions.join(", ");
+
+        let mut app = Self {
+            cache: ImageCache::new(config.cache.budget_bytes()),
+            duplicates: Duplicates::new(config.duplicates),
+            show_duplicate_report: false,
+            config,
+            index: Arc::new(Mutex::new(ImageIndex::load(Path::new(INDEX_FILE)))),
+            scanner: None,
+            scan_progress: ScanProgress::default(),
+            watcher: None,
+            show_first_found: false,
+            include_extensions_text,
+            exclude_extensions_text,
+            pattern_texts: HashMap::new(),
+            playlist: Playlist::default(),
+            history: History::load(Path::new(HISTORY_FILE)),
+            ratings: Ratings::load(Path::new(RATINGS_FILE)),
+            scroll_accumulator: 0.0,
+            current_image: None,
+            transition: None,
+            transition_pending: false,
+            adjusting_crop: false,
+            caption: None,
+            decoder: DecodePool::new(decoder::default_threads()),
+            decoding: HashMap::new(),
+            current_request: None,
+            last_update: Instant::now(),
+            show_settings: false,
+            current_image_path: None,
+            tray_rx,
+            on_albums_changed,
+            album_name_text: String::new(),
+            folder_rx,
+            folder_tx,
+            last_window_size: None,
+            show_drag_bar: false,
+            hover_leave_time: None,
+            last_screen_check: Instant::now(),
+            pixels_per_point: 1.0,
+        };
+
+        // 上次退出时显示的图片，不必等扫描完成
+        app.playlist.set_order(app.config.order);
+        app.playlist.set_date_filter(DateFilter::new(app.config.memories));
+        let restored = app.history.current().map(Path::to_path_buf);
+        app.scan_image_files(restored.is_none());
+        if let Some(path) = restored { app.resume_playlist_after(&path); app.load_image(path); }
+        app.notify_albums_changed();
+        app
+    }
+
+    /// Applies an album change made by `change` (switching, adding or
+    /// deleting): saves the config and rescans the folders of the now
+    /// active album. Nothing happens if `change` returns `false`.
+    fn change_album(&mut self, change: impl FnOnce(&mut AppConfig) -> bool) {
+        if !change(&mut self.config) {
+            return;
+        }
+        save_config(&self.config);
+        self.playlist.set_order(self.config.order);
+        self.playlist.set_date_filter(DateFilter::new(self.config.memories));
+        self.pattern_texts.clear();
+        self.scan_image_files(true);
+        self.last_update = Instant::now();
+        self.notify_albums_changed();
+    }
+
+    fn notify_albums_changed(&mut self) {
+        let names = self.config.album_names();
+        (self.on_albums_changed)(&names, &self.config.active_album);
+    }
+
+    /// Starts a background rescan, cancelling one that is still running.
+    /// With `show_new` the first image found replaces the current one.
+    fn scan_image_files(&mut self, show_new: bool) {
+        self.playlist.set_folders(&self.config.folder_weights());
+        self.duplicates = Duplicates::new(self.config.duplicates);
+        self.show_first_found = show_new;
+        // 先启动监视再扫描，这样扫描期间发生的变化也不会丢失
+        self.restart_watcher();
+        self.scanner = Some(Scanner::start(self.config.enabled_folders(), self.scan_filter(), self.index.clone()));
+    }
+
+    fn restart_watcher(&mut self) {
+        self.watcher = None;
+        self.watcher = FolderWatcher::start(self.config.watch_mode, &self.config.enabled_folders(), self.scan_filter(), self.index.clone());
+    }
+
+    /// The configured filter plus the images the user hid.
+    fn scan_filter(&self) -> ScanFilter {
+        ScanFilter { hidden: Arc::new(self.ratings.hidden()), ..ScanFilter::from_config(&self.config) }
+    }
+
+    /// Applies added, removed and renamed files reported by the folder watcher
+    /// without reshuffling the rest of the playlist.
+    fn poll_watcher(&mut self) {
+        let Some(watcher) = &self.watcher else { return };
+        let changes = watcher.poll();
+        if changes.is_empty() {
+            return;
+        }
+        for change in changes {
+            match change {
+                LibraryChange::Added(path) => self.add_to_playlist(path),
+                LibraryChange::Removed(path) => { self.remove_from_playlist(&path); self.history.remove(&path); self.cache.remove_under(&path); }
+                LibraryChange::Renamed { from, to } => {
+                    self.playlist.rename(&from, &to);
+                    self.duplicates.rename(&from, &to);
+                    self.history.rename(&from, &to);
+                    self.ratings.rename(&from, &to);
+                    self.ratings.save(Path::new(RATINGS_FILE));
+                    self.cache.remove_under(&from);
+                    if let Some(current) = &self.current_image_path && current.starts_with(&from) {
+                        self.current_image_path = Some(index::rebase(current, &from, &to));
+                    }
+                }
+            }
+        }
+        // 标题里的位置和总数 ({index}/{total}) 随播放列表变化
+        self.caption = self.caption_info();
+    }
+
+    /// Adds a found image unless it is hidden or a better copy of it is already shown.
+    fn add_to_playlist(&mut self, path: PathBuf) {
+        if self.ratings.is_hidden(&path) {
+            return;
+        }
+        if self.duplicates.settings().enabled {
+            let entry = self.index.lock().unwrap().get(&path).cloned();
+            if let Some(entry) = entry {
+                match self.duplicates.add(path.clone(), &entry) {
+                    Admission::Show => {}
+                    Admission::Skip => return,
+                    Admission::Replace(worse) => self.playlist.remove(&worse),
+                }
+            }
+        }
+        self.insert_into_playlist(path);
+    }
+
+    /// Removes an image (or a directory) and shows the next best copy of
+    /// removed images that had duplicates instead.
+    fn remove_from_playlist(&mut self, path: &Path) {
+        self.playlist.remove(path);
+        for promoted in self.duplicates.remove(path) { self.insert_into_playlist(promoted); }
+    }
+
+    fn insert_into_playlist(&mut self, path: PathBuf) {
+        if self.config.favor_ratings { self.playlist.set_image_weight(path.clone(), self.ratings.get(&path).playback_weight()); }
+        let index = self.index.lock().unwrap();
+        let entry = index.get(&path);
+        self.playlist.insert_with_entry(path.clone(), entry);
+    }
+
+    /// Changes the favorite flag, stars or hidden flag of the current photo.
+    /// A photo that gets hidden is dropped from the playlist, the history and
+    /// the cache, and the next one is shown right away.
+    fn rate_current_image(&mut self, change: impl FnOnce(&mut ImageRating)) {
+        let Some(path) = self.current_image_path.clone() else { return };
+        self.ratings.update(&path, change);
+        self.ratings.save(Path::new(RATINGS_FILE));
+        let rating = self.ratings.get(&path);
+        if rating.hidden {
+            self.remove_from_playlist(&path);
+            self.history.remove(&path);
+            self.cache.remove(&path);
+            // 监视器持有旧的过滤条件，重启后隐藏的图片被修改也不会再加入
+            self.restart_watcher();
+            self.show_next();
+        } else if self.config.favor_ratings {
+            self.playlist.set_image_weight(path, rating.playback_weight());
+        }
+    }
+
+    /// Sequential orders continue after `path` instead of starting over.
+    fn resume_playlist_after(&mut self, path: &Path) {
+        let key = SortKey::new(self.playlist.order(), path, self.index.lock().unwrap().get(path));
+        self.playlist.resume_after(key);
+    }
+
+    /// Moves newly found images into the playlist and saves the index once the scan is done.
+    fn poll_scanner(&mut self) {
+        let Some(scanner) = &mut self.scanner else { return };
+        let found = scanner.poll();
+        self.scan_progress = scanner.progress();
+        let finished = scanner.is_finished();
+
+        let any_found = !found.is_empty();
+        for path in found { self.add_to_playlist(path); }
+        if any_found { self.caption = self.caption_info(); }
+        if any_found && self.show_first_found {
+            self.show_first_found = false;
+            self.load_random_image();
+            self.last_update = Instant::now();
+        }
+        if finished {
+            self.scanner = None;
+            self.index.lock().unwrap().save(Path::new(INDEX_FILE));
+        }
+    }
+
+    /// Shows a new photo from the playlist and records it in the history.
+    fn load_random_image(&mut self) {
+        // "那年今日" 过了午夜换成新的一天
+        if self.playlist.date_filter().is_some_and(|filter| filter.today() != Day::today()) { self.refresh_date_filter(); }
+        if let Some(path) = self.playlist.next_image() {
+            self.history.push(path.clone(), self.config.history_length);
+            self.history.save(Path::new(HISTORY_FILE));
+            self.load_image(path);
+        }
+    }
+
+    /// Re-evaluates the memories selection for today, keeping the playlist.
+    fn refresh_date_filter(&mut self) {
+        let files = self.playlist.files();
+        self.playlist.set_date_filter(DateFilter::new(self.config.memories));
+        for path in files { self.add_to_playlist(path); }
+    }
+
+    /// Steps forward through the history after going back, or shows a new
+    /// photo when already at the newest one.
+    fn show_next(&mut self) {
+        match self.history.forward() {
+            Some(path) => self.load_image(path),
+            None => self.load_random_image(),
+        }
+        self.last_update = Instant::now();
+    }
+
+    fn show_previous(&mut self) {
+        if let Some(path) = self.history.back() {
+            self.load_image(path);
+            self.last_update = Instant::now();
+        }
+    }
+
+    /// Back/forward via the mouse side buttons, the scroll wheel and the arrow keys.
+    fn handle_navigation_input(&mut self, ctx: &egui::Context) {
+        let (back, forward, scroll) = ctx.input(|i| (
+            i.pointer.button_pressed(egui::PointerButton::Extra1) || i.key_pressed(egui::Key::ArrowLeft),
+            i.pointer.button_pressed(egui::PointerButton::Extra2) || i.key_pressed(egui::Key::ArrowRight),
+            i.scroll_delta.y,
+        ));
+        // 向上滚动是上一张，向下滚动是下一张
+        self.scroll_accumulator += scroll;
+        let scroll_step = 50.0;
+        if back || self.scroll_accumulator >= scroll_step { self.scroll_accumulator = 0.0; self.show_previous(); }
+        else if forward || self.scroll_accumulator <= -scroll_step { self.scroll_accumulator = 0.0; self.show_next(); }
+    }
+
+    /// Shows `path`: at once when it is cached, otherwise as soon as its
+    /// decode finishes. Then starts decoding the images after it.
+    fn load_image(&mut self, path: PathBuf) {
+        // 用户已经切走了，之前等待的图片不再需要
+        if let Some(previous) = self.current_request.take() { self.cancel_decode(previous); }
+        self.current_image_path = Some(path.clone());
+        self.transition_pending = true;
+        self.adjusting_crop = false;
+        if let Some(cached) = self.cache.get(&path).map(ShownImage::restarted) {
+            self.present(cached);
+        } else if let Some(&id) = self.decoding.get(&path) {
+            // 已在预取
+            self.current_request = Some(id);
+        } else {
+            self.current_request = Some(self.decode(path, true));
+        }
+        self.prefetch();
+    }
+
+    /// Decodes the current image again, e.g. after it was rotated.
+    fn reload_image(&mut self) {
+        let Some(path) = self.current_image_path.clone() else { return };
+        if let Some(previous) = self.current_request.take() { self.cancel_decode(previous); }
+        if let Some(&id) = self.decoding.get(&path) { self.cancel_decode(id); }
+        self.cache.remove(&path);
+        self.current_request = Some(self.decode(path, true));
+    }
+
+    /// Decodes the next few images of the playlist and cancels prefetches
+    /// that fell out of that window.
+    fn prefetch(&mut self) {
+        let upcoming = self.playlist.upcoming(self.config.cache.prefetch_count).to_vec();
+        let stale: Vec<RequestId> = self.decoding.iter()
+            .filter(|(path, id)| Some(**id) != self.current_request && !upcoming.contains(path))
+            .map(|(_, id)| *id)
+            .collect();
+        for id in stale { self.cancel_decode(id); }
+        for path in upcoming {
+            if !self.cache.contains(&path) && !self.decoding.contains_key(&path) { self.decode(path, false); }
+        }
+    }
+
+    fn cancel_decode(&mut self, id: RequestId) {
+        self.decoder.cancel(id);
+        self.decoding.retain(|_, request| *request != id);
+    }
+
+    /// Queues a decode on the pool; the result is picked up in `update`.
+    fn decode(&mut self, path: PathBuf, urgent: bool) -> RequestId {
+        let (user_rotation, display_size) = self.index.lock().unwrap().get(&path).map_or((0, None), |e| (e.user_rotation, Some(e.display_size())));
+        let options = DecodeOptions {
+            user_rotation,
+            animation: self.config.animation.clone(),
+            target_size: display_size.map(|(w, h)| layout::display_pixels(&self.config, Vec2::new(w as f32, h as f32), self.pixels_per_point)),
+            filter: self.config.resample_filter,
+            blurred_background: self.config.fit_mode == FitMode::Letterbox && self.config.letterbox_fill == LetterboxFill::Blurred,
+        };
+        let id = self.decoder.request(path.clone(), options, urgent);
+        self.decoding.insert(path, id);
+        id
+    }
+
+    /// Uploads finished decodes into the cache and shows the one the widget
+    /// is waiting for.
+    fn poll_decoder(&mut self, ctx: &egui::Context) {
+        for result in self.decoder.poll() {
+            self.decoding.remove(&result.path);
+            let is_current = self.current_request == Some(result.id);
+            if is_current { self.current_request = None; }
+            let Some(image) = result.image else { continue };
+            let shown = ShownImage::upload(ctx, &result.path.to_string_lossy(), image);
+            if is_current { self.present(shown.clone()); }
+            let bytes = shown.byte_size();
+            self.cache.insert(result.path, shown, bytes);
+        }
+    }
+
+    /// Puts `shown` on screen, with a transition from the previous image when
+    /// the widget switched to another image.
+    fn present(&mut self, mut shown: ShownImage) {
+        let ken_burns = self.config.ken_burns;
+        shown.motion = (ken_burns.enabled && shown.frames.len() == 1).then(|| ken_burns.new_move(&mut rand::thread_rng()));
+        shown.focus = self.focus_of_current_image();
+        let previous = self.current_image.replace(shown);
+        self.caption = self.caption_info();
+        if std::mem::take(&mut self.transition_pending) && self.config.transition.is_enabled() && let Some(outgoing) = previous {
+            self.transition = Some(ActiveTransition { outgoing, started: Instant::now() });
+        }
+    }
+
+    /// Texture coordinates of `shown` in a window of `size`, following its
+    /// Ken Burns move in `Cover` mode.
+    fn image_uv(&self, shown: &ShownImage, size: Vec2) -> Rect {
+        let texture_size = shown.texture().size_vec2();
+        match shown.motion {
+            Some(motion) if self.config.fit_mode == FitMode::Cover => {
+                let display_time = Duration::from_secs(self.config.refresh_interval);
+                motion.uv(texture_size, size, self.config.ken_burns.progress(shown.shown_at.elapsed(), display_time))
+            }
+            _ if self.config.fit_mode == FitMode::Cover => layout::focused_cover_uv(texture_size, size, shown.focus.unwrap_or(pos2(0.5, 0.5))),
+            _ => layout::image_uv(self.config.fit_mode, texture_size, size),
+        }
+    }
+
+    /// The caption values of the current image, `None` when captions are off.
+    /// Reads no files, so it is redone whenever the playlist changes.
+    fn caption_info(&self) -> Option<CaptionInfo> {
+        let path = self.current_image_path.as_ref().filter(|_| self.config.caption.enabled)?;
+        let exif = self.current_image.as_ref().and_then(|shown| shown.exif.as_ref());
+        let index = self.index.lock().unwrap();
+        Some(CaptionInfo::new(path, index.get(path), exif, self.playlist.position(path), &self.config.active_album, platform::utc_offset_seconds()))
+    }
+
+    fn focus_of_current_image(&self) -> Option<Pos2> {
+        let path = self.current_image_path.as_ref()?;
+        self.index.lock().unwrap().get(path).and_then(IndexEntry::focus_point).map(|(x, y)| pos2(x, y))
+    }
+
+    /// Moves the crop of the current image along with the pointer while the
+    /// user adjusts it.
+    fn drag_focus(&mut self, delta: Vec2, window_size: Vec2) {
+        let Some(shown) = &mut self.current_image else { return };
+        let texture_size = shown.texture().size_vec2();
+        let uv = layout::focused_cover_uv(texture_size, window_size, shown.focus.unwrap_or(pos2(0.5, 0.5)));
+        // 图片跟着指针走，裁剪区域反向移动；焦点限制在裁剪区域能到达的范围内
+        let moved = uv.translate(-delta / window_size * uv.size());
+        shown.focus = Some(layout::focused_cover_uv(texture_size, window_size, moved.center()).center());
+    }
+
+    /// Leaves the crop adjustment. `Some(focus)` stores the user's focus
+    /// point (`Some(None)` goes back to the detected one), `None` discards
+    /// the change.
+    fn end_crop_adjustment(&mut self, user_focus: Option<Option<Pos2>>) {
+        self.adjusting_crop = false;
+        if let Some(focus) = user_focus && let Some(path) = &self.current_image_path {
+            let mut index = self.index.lock().unwrap();
+            index.set_user_focus(path, focus.map(|point| (point.x, point.y)));
+            index.save(Path::new(INDEX_FILE));
+        }
+        let focus = self.focus_of_current_image();
+        if let Some(shown) = &mut self.current_image { shown.focus = focus; }
+    }
+
+    /// What is painted for `shown` in `window` (without a transition): the
+    /// bars of a letterboxed image, then the image itself.
+    fn image_layers<'a>(&self, shown: &'a ShownImage, window: Rect) -> Vec<(Fill<'a>, Layer)> {
+        let texture = shown.texture();
+        let mut layers = Vec::new();
+        if self.config.fit_mode == FitMode::Letterbox {
+            layers.push(match (&shown.background, self.config.letterbox_fill) {
+                (Some(background), LetterboxFill::Blurred) => (Fill::Texture(background), Layer::placed(FitMode::Cover, background.size_vec2(), window)),
+                // 切换模式前缓存的图片没有模糊背景，先用纯色
+                _ => {
+                    let [r, g, b] = self.config.letterbox_color;
+                    (Fill::Color(Color32::from_rgb(r, g, b)), Layer::placed(FitMode::Cover, window.size(), window))
+                }
+            });
+        }
+        layers.push((Fill::Texture(texture), Layer { uv: self.image_uv(shown, window.size()), ..Layer::placed(self.config.fit_mode, texture.size_vec2(), window) }));
+        layers
+    }
+
+    /// Adds `quarter_turns` (clockwise) to the manual rotation of the current
+    /// image, stores it in the index and shows the image again.
+    fn rotate_current_image(&mut self, quarter_turns: u8) {
+        let Some(path) = self.current_image_path.clone() else { return };
+        {
+            let mut index = self.index.lock().unwrap();
+            let Some(rotation) = index.get(&path).map(|e| e.user_rotation) else { return };
+            index.set_user_rotation(&path, if quarter_turns == 0 { 0 } else { rotation + quarter_turns });
+            index.save(Path::new(INDEX_FILE));
+        }
+        self.reload_image();
+    }
+}
+
+impl eframe::App for PhotoWidget {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        if self.last_screen_check.elapsed() > Duration::from_secs(1) {
+            if let (Some(window_pos), Some(screen_size)) = (
+                frame.info().window_info.position,
+                frame.info().window_info.monitor_size,
+            ) {
+                let window_size = frame.info().window_info.size;
+                if let Some(new_pos) = layout::clamp_to_screen(window_pos, window_size, screen_size) { frame.set_window_pos(new_pos); }
+            }
+            self.last_screen_check = Instant::now();
+        }
+
+        if let Ok(msg) = self.tray_rx.try_recv() {
+            match msg {
+                TrayMessage::ShowSettings => { self.show_settings = true; frame.set_decorations(true);frame.focus();  }
+                TrayMessage::Previous => self.show_previous(),
+                TrayMessage::Next => self.show_next(),
+                TrayMessage::FocusWindow => {
+                    frame.focus();
+                }
+                TrayMessage::Quit => { frame.close(); }
+                TrayMessage::ToggleFavorite => self.rate_current_image(|rating| rating.favorite = !rating.favorite),
+                TrayMessage::Rate(stars) => self.rate_current_image(|rating| rating.stars = stars),
+                TrayMessage::HideCurrent => self.rate_current_image(|rating| rating.hidden = true),
+                TrayMessage::SwitchAlbum(name) => {
+                    let active = self.config.active_album.clone();
+                    self.change_album(|config| config.switch_album(&name));
+                    // 点击已勾选的相册时托盘菜单会自己取消勾选，没有切换也要重新同步
+                    if self.config.active_album == active { self.notify_albums_changed(); }
+                }
+            }
+        }
+        if let Ok(folder) = self.folder_rx.try_recv() && !self.config.folders.iter().any(|f| f.path == folder) { self.config.folders.push(FolderEntry::new(folder)); self.scan_image_files(true); }
+        self.poll_scanner();
+        self.poll_watcher();
+        self.pixels_per_point = ctx.pixels_per_point();
+        self.poll_decoder(ctx);
+        if self.transition.as_ref().is_some_and(|transition| transition.started.elapsed() >= self.config.transition.duration()) { self.transition = None; }
+        // 窗口变大 (改了预设尺寸或移到了缩放比例更高的显示器) 时按新尺寸重新解码
+        if !self.show_settings
+            && let Some(shown) = &mut self.current_image
+            && let Some(decoded) = shown.downscaled_to
+        {
+            let wanted = layout::display_pixels(&self.config, shown.texture().size_vec2(), self.pixels_per_point);
+            if wanted.x > decoded.x + 1.0 || wanted.y > decoded.y + 1.0 {
+                shown.downscaled_to = None;
+                self.reload_image();
+            }
+        }
+        // 动图至少完整播放一遍后才切换
+        let animation_done = !self.config.animation.wait_for_loop || self.current_image.as_ref().is_none_or(ShownImage::has_looped);
+        if self.config.refresh_interval > 0 && self.last_update.elapsed().as_secs() >= self.config.refresh_interval && !self.show_settings && !self.adjusting_crop && animation_done {
+            self.show_next();
+        }
+        if !self.show_settings && !self.adjusting_crop { self.handle_navigation_input(ctx); }
+        let new_size = if self.show_settings { layout::SETTINGS_WINDOW_SIZE } else {
+            layout::window_size(&self.config, self.current_image.as_ref().map(|image| image.texture().size_vec2()))
+        };
+
+        if let Some(old_size) = self.last_window_size
+            && old_size != new_size
+            && let Some(current_pos) = frame.info().window_info.position
+        {
+            frame.set_window_pos(layout::anchored_position(self.config.resize_anchor, current_pos, old_size, new_size));
+        }
+        frame.set_window_size(new_size);
+        self.last_window_size = Some(new_size);
+        frame.set_always_on_top(self.config.always_on_top);
+
+        if self.show_settings {
+            self.show_drag_bar = false;
+            egui::CentralPanel::default().show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.heading("Settings"); ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Album:");
+                        let mut selected = self.config.active_album.clone();
+                        egui::ComboBox::from_id_source("album").selected_text(selected.clone()).show_ui(ui, |ui| {
+                            for name in self.config.album_names() { ui.selectable_value(&mut selected, name.clone(), name); }
+                        });
+                        if selected != self.config.active_album { self.change_album(|config| config.switch_album(&selected)); }
+                        if ui.add_enabled(!self.config.albums.is_empty(), egui::Button::new("Delete")).on_hover_text("Delete this album (its folders stay on disk)").clicked() {
+                            self.change_album(AppConfig::remove_album);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut self.album_name_text).hint_text("Album name").desired_width(160.0));
+                        if ui.button("New Album").clicked() { let name = self.album_name_text.clone(); self.change_album(|config| config.add_album(&name)); }
+                        if ui.button("Rename").clicked() && self.config.rename_album(&self.album_name_text) { save_config(&self.config); self.notify_albums_changed(); }
+                    });
+                    ui.label(egui::RichText::new("Each album has its own folders, filters, memories and playback order.").small());
+                    ui.separator();
+                    if ui.button("Add Folder").clicked() { let folder_tx = self.folder_tx.clone(); thread::spawn(move || { if let Some(folder) = FileDialog::new().pick_folder() { let _ = folder_tx.send(folder); } }); }
+                    ui.label("Image Folders:");
+                    let mut folder_to_remove = None;
+                    let mut folders_toggled = false;
+                    for (i, folder) in self.config.folders.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.button("Remove").clicked() { folder_to_remove = Some(i); }
+                            folders_toggled |= ui.checkbox(&mut folder.enabled, "").on_hover_text("Include this folder").changed();
+                            // 权重只影响随机播放时各文件夹被抽中的比例
+                            ui.add_enabled(folder.enabled, egui::DragValue::new(&mut folder.weight).speed(0.1).clamp_range(0.1..=100.0).prefix("Weight: "))
+                                .on_hover_text("Share of the shown photos relative to the other folders, regardless of how many photos each holds");
+                            ui.label(folder.path.to_string_lossy());
+                        });
+                        let texts = self.pattern_texts.entry(folder.path.clone()).or_insert_with(|| [folder.include.join("\n"), folder.exclude.join("\n")]);
+                        egui::CollapsingHeader::new("Scan Rules").id_source(("scan_rules", i)).show(ui, |ui| {
+                            let lines = |text: &str| text.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect();
+                            ui.label("Only include (one glob per line, empty for all images):");
+                            if ui.add(egui::TextEdit::multiline(&mut texts[0]).desired_rows(2)).changed() { folder.include = lines(&texts[0]); }
+                            ui.label("Exclude files and folders:");
+                            if ui.add(egui::TextEdit::multiline(&mut texts[1]).desired_rows(2)).changed() { folder.exclude = lines(&texts[1]); }
+                            for (pattern, error) in folder.include.iter().chain(&folder.exclude).filter_map(|p| library::pattern_error(p).map(|e| (p, e))) {
+                                ui.colored_label(Color32::LIGHT_RED, format!("Invalid pattern \"{pattern}\": {error}"));
+                            }
+                            ui.horizontal(|ui| {
+                                let mut limited = folder.max_depth.is_some();
+                                if ui.checkbox(&mut limited, "Limit subfolder depth").changed() { folder.max_depth = limited.then_some(0); }
+                                if let Some(depth) = &mut folder.max_depth { ui.add(egui::DragValue::new(depth).clamp_range(0..=64).suffix(" levels")); }
+                            });
+                            ui.checkbox(&mut folder.include_hidden, "Include hidden files and folders");
+                            ui.checkbox(&mut folder.follow_symlinks, "Follow symbolic links");
+                            ui.label(egui::RichText::new("Patterns without a / match names, the others paths relative to the folder. Changes apply on Save and Close.").small());
+                        });
+                    }
+                    if let Some(i) = folder_to_remove { self.config.folders.remove(i); self.scan_image_files(false); }
+                    else if folders_toggled { self.scan_image_files(false); }
+                    let progress = self.scan_progress;
+                    if self.scanner.is_some() {
+                        ui.horizontal(|ui| { ui.spinner(); ui.label(format!("Scanning... {} files seen, {} images accepted, {} errors", progress.files_seen, progress.images_accepted, progress.errors)); });
+                    } else if progress.finished {
+                        ui.label(format!("{} images found ({} files seen, {} errors)", progress.images_accepted, progress.files_seen, progress.errors));
+                    }
+                    ui.separator();
+                    let old_watch_mode = self.config.watch_mode;
+                    ui.horizontal(|ui| { ui.label("Watch folders:"); ui.radio_value(&mut self.config.watch_mode, WatchMode::Off, "Off"); ui.radio_value(&mut self.config.watch_mode, WatchMode::Native, "Live"); ui.radio_value(&mut self.config.watch_mode, WatchMode::Polling, "Polling (network drives)"); });
+                    if self.config.watch_mode != old_watch_mode { self.restart_watcher(); }
+                    ui.separator();
+                    ui.checkbox(&mut self.config.always_on_top, "Always on Top"); ui.separator();
+                    let old_filter = self.config.orientation_filter;
+                    ui.label("Image Orientation:");
+                    ui.horizontal(|ui| { ui.radio_value(&mut self.config.orientation_filter, ImageOrientationFilter::Both, "Both"); ui.radio_value(&mut self.config.orientation_filter, ImageOrientationFilter::Landscape, "Landscape"); ui.radio_value(&mut self.config.orientation_filter, ImageOrientationFilter::Portrait, "Portrait"); ui.radio_value(&mut self.config.orientation_filter, ImageOrientationFilter::Square, "Square"); });
+                    if self.config.orientation_filter != old_filter { self.scan_image_files(true); }
+                    egui::CollapsingHeader::new("Size and Shape Filters").show(ui, |ui| {
+                        let limits = &mut self.config.image_limits;
+                        ui.horizontal(|ui| { ui.label("Square tolerance:"); ui.add(egui::Slider::new(&mut limits.square_tolerance, 0.0..=0.25).custom_formatter(|v, _| format!("{:.0} %", v * 100.0))); });
+                        ui.horizontal(|ui| { ui.label("Minimum size (px):"); ui.add(egui::DragValue::new(&mut limits.min_width).clamp_range(0..=20_000).prefix("W ")); ui.add(egui::DragValue::new(&mut limits.min_height).clamp_range(0..=20_000).prefix("H ")); });
+                        optional_value(ui, &mut limits.min_aspect_ratio, "Min aspect ratio (width / height)", 0.5, 0.1..=10.0);
+                        optional_value(ui, &mut limits.max_aspect_ratio, "Max aspect ratio (width / height)", 2.0, 0.1..=10.0);
+                        optional_value(ui, &mut limits.min_file_size_kb, "Min file size (KB)", 50, 1..=1_000_000);
+                        optional_value(ui, &mut limits.max_file_size_mb, "Max file size (MB)", 50, 1..=100_000);
+                        ui.label(egui::RichText::new("Changes apply on Save and Close.").small());
+                    });
+                    ui.separator();
+                    ui.label("Playback Order:");
+                    ui.horizontal(|ui| { ui.radio_value(&mut self.config.order, PlaybackOrder::Shuffle, "Shuffle"); ui.radio_value(&mut self.config.order, PlaybackOrder::Random, "Random"); ui.radio_value(&mut self.config.order, PlaybackOrder::ByFolder, "By Folder"); });
+                    ui.horizontal(|ui| { ui.radio_value(&mut self.config.order, PlaybackOrder::ByName, "By Name"); ui.radio_value(&mut self.config.order, PlaybackOrder::ByModified, "By Date Modified"); ui.radio_value(&mut self.config.order, PlaybackOrder::ByCaptureDate, "By Date Taken"); });
+                    ui.checkbox(&mut self.config.favor_ratings, "Show favorites and higher-rated photos more often").on_hover_text("Random orders only. Each star adds one share, favorites count double.");
+                    let hidden_count = self.ratings.hidden().len();
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{hidden_count} photos hidden"));
+                        if ui.add_enabled(hidden_count > 0, egui::Button::new("Unhide All")).clicked() {
+                            self.ratings.unhide_all();
+                            self.ratings.save(Path::new(RATINGS_FILE));
+                            self.scan_image_files(false);
+                        }
+                    });
+                    ui.separator();
+                    egui::CollapsingHeader::new("Memories").show(ui, |ui| {
+                        let memories = &mut self.config.memories;
+                        ui.horizontal(|ui| { ui.radio_value(&mut memories.selection, DateSelection::All, "All photos"); ui.radio_value(&mut memories.selection, DateSelection::OnThisDay, "On this day in earlier years"); ui.radio_value(&mut memories.selection, DateSelection::Range, "Taken between"); });
+                        ui.add_enabled_ui(memories.selection == DateSelection::OnThisDay, |ui| {
+                            ui.horizontal(|ui| { ui.label("Days before and after:"); ui.add(egui::DragValue::new(&mut memories.days_around).clamp_range(0..=30)); });
+                        });
+                        ui.add_enabled_ui(memories.selection == DateSelection::Range, |ui| {
+                            for (label, day) in [("From:", &mut memories.from), ("To:", &mut memories.to)] {
+                                ui.horizontal(|ui| {
+                                    ui.label(label);
+                                    ui.add(egui::DragValue::new(&mut day.year).clamp_range(1900..=2100));
+                                    ui.add(egui::DragValue::new(&mut day.month).clamp_range(1..=12).prefix("-"));
+                                    ui.add(egui::DragValue::new(&mut day.day).clamp_range(1..=31).prefix("-"));
+                                });
+                            }
+                        });
+                        if self.playlist.date_filter().is_some() {
+                            let matching = self.playlist.matching_len();
+                            let status = if matching == 0 { "No photos match, showing all photos.".to_string() } else { format!("{matching} of {} photos match.", self.playlist.len()) };
+                            ui.label(egui::RichText::new(status).small());
+                        }
+                        ui.label(egui::RichText::new("Uses the date a photo was taken, or the file date when it has none. Changes apply on Save and Close.").small());
+                    });
+                    egui::CollapsingHeader::new("Duplicates").show(ui, |ui| {
+                        let settings = &mut self.config.duplicates;
+                        ui.checkbox(&mut settings.enabled, "Show each photo only once").on_hover_text("Hashes every image while scanning; of identical copies the one with the highest resolution is shown.");
+                        ui.add_enabled_ui(settings.enabled, |ui| {
+                            ui.checkbox(&mut settings.near_duplicates, "Also detect resized and re-encoded copies (slower first scan)");
+                            ui.add_enabled_ui(settings.near_duplicates, |ui| {
+                                ui.horizontal(|ui| { ui.label("Similarity tolerance:"); ui.add(egui::DragValue::new(&mut settings.max_distance).clamp_range(0..=16)); });
+                            });
+                        });
+                        let groups = self.duplicates.groups();
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{} copies skipped in {} groups", self.duplicates.skipped(), groups.len()));
+                            if ui.add_enabled(!groups.is_empty(), egui::Button::new(if self.show_duplicate_report { "Hide Report" } else { "Show Report" })).clicked() { self.show_duplicate_report = !self.show_duplicate_report; }
+                        });
+                        if self.show_duplicate_report {
+                            egui::ScrollArea::vertical().id_source("duplicate_report").max_height(200.0).show(ui, |ui| {
+                                let index = self.index.lock().unwrap();
+                                for group in &groups {
+                                    for (i, path) in group.iter().enumerate() {
+                                        let size = index.get(path).map(|entry| entry.display_size()).map_or_else(String::new, |(w, h)| format!(" ({w}×{h})"));
+                                        let text = format!("{} {}{size}", if i == 0 { "Shown:" } else { "    Skipped:" }, path.display());
+                                        ui.label(egui::RichText::new(text).small());
+                                    }
+                                    ui.separator();
+                                }
+                            });
+                        }
+                        ui.label(egui::RichText::new("Changes apply on Save and Close.").small());
+                    });
+                    egui::CollapsingHeader::new("Supported Formats").show(ui, |ui| {
+                        let split = |text: &str| text.split([',', ' ']).map(str::trim).filter(|ext| !ext.is_empty()).map(str::to_string).collect();
+                        ui.horizontal(|ui| {
+                            ui.label("Also include:");
+                            if ui.text_edit_singleline(&mut self.include_extensions_text).changed() { self.config.formats.include_extensions = split(&self.include_extensions_text); }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Exclude:");
+                            if ui.text_edit_singleline(&mut self.exclude_extensions_text).changed() { self.config.formats.exclude_extensions = split(&self.exclude_extensions_text); }
+                        });
+                        ui.checkbox(&mut self.config.formats.sniff_content, "Detect images with missing or unknown extensions by content");
+                        ui.label(egui::RichText::new(format!("Accepted: {}", FormatFilter::new(&self.config.formats).extensions().join(", "))).small());
+                        ui.label(egui::RichText::new("Changes apply on Save and Close.").small());
+                    });
+                    egui::CollapsingHeader::new("Animations").show(ui, |ui| {
+                        let animation = &mut self.config.animation;
+                        ui.checkbox(&mut animation.enabled, "Play animated GIF, PNG and WebP");
+                        ui.add_enabled_ui(animation.enabled, |ui| {
+                            ui.checkbox(&mut animation.wait_for_loop, "Show the whole animation at least once before refreshing");
+                            ui.horizontal(|ui| { ui.label("Max frames:"); ui.add(egui::DragValue::new(&mut animation.max_frames).clamp_range(2..=10_000)); });
+                            ui.horizontal(|ui| { ui.label("Max memory per image (MB):"); ui.add(egui::DragValue::new(&mut animation.max_memory_mb).clamp_range(16..=4096)); });
+                        });
+                    });
+                    egui::CollapsingHeader::new("Caption").show(ui, |ui| {
+                        let caption = &mut self.config.caption;
+                        ui.checkbox(&mut caption.enabled, "Show a caption over the photo");
+                        ui.add_enabled_ui(caption.enabled, |ui| {
+                            ui.add(egui::TextEdit::multiline(&mut caption.template).desired_rows(2).desired_width(f32::INFINITY));
+                            ui.label(egui::RichText::new(format!("Placeholders: {}", PLACEHOLDERS.join(" "))).small());
+                            ui.horizontal(|ui| { ui.label("Position:"); ui.radio_value(&mut caption.position, CaptionPosition::TopLeft, "Top Left"); ui.radio_value(&mut caption.position, CaptionPosition::TopRight, "Top Right"); });
+                            ui.horizontal(|ui| { ui.radio_value(&mut caption.position, CaptionPosition::BottomLeft, "Bottom Left"); ui.radio_value(&mut caption.position, CaptionPosition::BottomCenter, "Bottom Center"); ui.radio_value(&mut caption.position, CaptionPosition::BottomRight, "Bottom Right"); });
+                            ui.horizontal(|ui| { ui.label("Show:"); ui.radio_value(&mut caption.visibility, CaptionVisibility::OnHover, "On hover (fades in)"); ui.radio_value(&mut caption.visibility, CaptionVisibility::Always, "Always"); });
+                            ui.add(egui::Slider::new(&mut caption.font_size, 8.0..=32.0).text("Font size"));
+                        });
+                    });
+                    egui::CollapsingHeader::new("Cache and History").show(ui, |ui| {
+                        ui.horizontal(|ui| { ui.label("Images to decode in advance:"); ui.add(egui::DragValue::new(&mut self.config.cache.prefetch_count).clamp_range(0..=10)); });
+                        ui.horizontal(|ui| { ui.label("Memory budget (MB):"); ui.add(egui::DragValue::new(&mut self.config.cache.memory_budget_mb).clamp_range(32..=4096)); });
+                        ui.horizontal(|ui| { ui.label("History length:"); ui.add(egui::DragValue::new(&mut self.config.history_length).clamp_range(1..=10_000)); });
+                        ui.label(egui::RichText::new(format!("{} images cached, {:.1} MB", self.cache.len(), self.cache.used_bytes() as f64 / (1024.0 * 1024.0))).small());
+                    });
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label("Current Image:");
+                        ui.add_enabled_ui(self.current_image_path.is_some(), |ui| {
+                            if ui.button("⟲ Rotate Left").clicked() { self.rotate_current_image(3); }
+                            if ui.button("⟳ Rotate Right").clicked() { self.rotate_current_image(1); }
+                            if ui.button("Reset Rotation").clicked() { self.rotate_current_image(0); }
+                        });
+                    });
+                    ui.separator();
+                    ui.label("Refresh Interval (0 to disable):");
+                    ui.horizontal(|ui| { ui.add(egui::DragValue::new(&mut self.config.refresh_value).speed(1.0).clamp_range(0..=u64::MAX)); ui.radio_value(&mut self.config.refresh_unit, TimeUnit::Seconds, "Seconds"); ui.radio_value(&mut self.config.refresh_unit, TimeUnit::Minutes, "Minutes"); ui.radio_value(&mut self.config.refresh_unit, TimeUnit::Hours, "Hours"); });
+                    ui.separator();
+                    ui.label("Landscape Base Dimensions:");
+                    ui.add(egui::Slider::new(&mut self.config.landscape_width, 200.0..=1000.0).text("Width"));
+                    ui.add(egui::Slider::new(&mut self.config.landscape_height, 200.0..=1000.0).text("Height (Cover and Letterbox)"));
+                    ui.separator();
+                    ui.label("Portrait Base Dimensions:");
+                    ui.add(egui::Slider::new(&mut self.config.portrait_width, 200.0..=1000.0).text("Width"));
+                    ui.add(egui::Slider::new(&mut self.config.portrait_height, 200.0..=1000.0).text("Height (Cover and Letterbox)"));
+                    ui.separator();
+                    ui.label("Image Fit Mode:");
+                    ui.horizontal(|ui| { ui.radio_value(&mut self.config.fit_mode, FitMode::Cover, "Cover (Fill and Crop)"); ui.radio_value(&mut self.config.fit_mode, FitMode::Contain, "Contain (Fit and Resize Window)"); ui.radio_value(&mut self.config.fit_mode, FitMode::Letterbox, "Letterbox (Fit, Fixed Size)"); });
+                    if self.config.fit_mode == FitMode::Letterbox {
+                        ui.horizontal(|ui| {
+                            ui.label("Fill bars with:");
+                            ui.radio_value(&mut self.config.letterbox_fill, LetterboxFill::Blurred, "Blurred image");
+                            ui.radio_value(&mut self.config.letterbox_fill, LetterboxFill::Color, "Color");
+                            if self.config.letterbox_fill == LetterboxFill::Color { ui.color_edit_button_srgb(&mut self.config.letterbox_color); }
+                        });
+                    }
+                    ui.separator();
+                    ui.label("Transition:");
+                    let transition = &mut self.config.transition;
+                    ui.horizontal(|ui| { ui.radio_value(&mut transition.kind, TransitionKind::None, "None"); ui.radio_value(&mut transition.kind, TransitionKind::Crossfade, "Crossfade"); ui.radio_value(&mut transition.kind, TransitionKind::Slide, "Slide"); ui.radio_value(&mut transition.kind, TransitionKind::ZoomFade, "Zoom and Fade"); ui.radio_value(&mut transition.kind, TransitionKind::Dissolve, "Dissolve"); });
+                    ui.add_enabled_ui(transition.kind != TransitionKind::None, |ui| {
+                        ui.horizontal(|ui| { ui.label("Duration (ms):"); ui.add(egui::DragValue::new(&mut transition.duration_ms).speed(10.0).clamp_range(50..=5000)); });
+                    });
+                    if transition.kind == TransitionKind::Slide {
+                        ui.horizontal(|ui| { ui.label("Slide in from:"); ui.radio_value(&mut transition.direction, SlideDirection::Right, "Right"); ui.radio_value(&mut transition.direction, SlideDirection::Left, "Left"); ui.radio_value(&mut transition.direction, SlideDirection::Top, "Top"); ui.radio_value(&mut transition.direction, SlideDirection::Bottom, "Bottom"); });
+                    }
+                    ui.add_enabled_ui(self.config.fit_mode == FitMode::Cover, |ui| {
+                        ui.checkbox(&mut self.config.smart_crop, "Smart crop: find the subject of each photo while scanning (slower first scan)").on_hover_text("Right-click a photo and choose Adjust Crop to set the crop by hand.");
+                        let ken_burns = &mut self.config.ken_burns;
+                        ui.checkbox(&mut ken_burns.enabled, "Ken Burns effect (slow pan and zoom, Cover only)");
+                        ui.add_enabled_ui(ken_burns.enabled, |ui| {
+                            ui.horizontal(|ui| { ui.radio_value(&mut ken_burns.path, KenBurnsPath::Random, "Random"); ui.radio_value(&mut ken_burns.path, KenBurnsPath::ZoomIn, "Zoom In"); ui.radio_value(&mut ken_burns.path, KenBurnsPath::ZoomOut, "Zoom Out"); ui.radio_value(&mut ken_burns.path, KenBurnsPath::Pan, "Pan"); });
+                            ui.add(egui::Slider::new(&mut ken_burns.max_zoom, 1.0..=2.0).text("Zoom"));
+                            ui.add(egui::Slider::new(&mut ken_burns.speed, 0.25..=4.0).logarithmic(true).text("Speed (passes per image)"));
+                        });
+                    });
+                    ui.separator();
+                    ui.label("Scaling Quality:");
+                    ui.horizontal(|ui| { ui.radio_value(&mut self.config.resample_filter, ResampleFilter::Nearest, "Fastest"); ui.radio_value(&mut self.config.resample_filter, ResampleFilter::Triangle, "Bilinear"); ui.radio_value(&mut self.config.resample_filter, ResampleFilter::CatmullRom, "Bicubic"); ui.radio_value(&mut self.config.resample_filter, ResampleFilter::Lanczos3, "Lanczos (Best)"); });
+                    ui.separator();
+                    ui.label("Resize Anchor Point:");
+                    ui.horizontal(|ui| { ui.radio_value(&mut self.config.resize_anchor, ResizeAnchor::Center, "Keep Center"); ui.radio_value(&mut self.config.resize_anchor, ResizeAnchor::TopLeft, "Keep Top-Left"); });
+                    ui.horizontal(|ui| { ui.radio_value(&mut self.config.resize_anchor, ResizeAnchor::TopRight, "Keep Top-Right"); ui.radio_value(&mut self.config.resize_anchor, ResizeAnchor::BottomLeft, "Keep Bottom-Left"); });
+                    ui.horizontal(|ui| { ui.radio_value(&mut self.config.resize_anchor, ResizeAnchor::BottomRight, "Keep Bottom-Right"); });
+                    ui.separator();
+
+                    if ui.button("Save and Close").clicked() {
+                        self.config.apply_refresh_value();
+
+                        // --- 新增：在保存设置时，同时保存当前窗口的位置 ---
+                        if let Some(pos) = frame.info().window_info.position {
+                            self.config.window_pos = Some((pos.x, pos.y));
+                        }
+
+                        save_config(&self.config);
+                        // 解码参数可能变了 (缩放质量、动图设置、尺寸)，缓存的图片作废
+                        self.cache.clear();
+                        self.cache.set_budget(self.config.cache.budget_bytes());
+                        self.history.set_max_len(self.config.history_length);
+                        if self.playlist.order() != self.config.order {
+                            self.playlist.set_order(self.config.order);
+                            if let Some(path) = self.current_image_path.clone() { self.resume_playlist_after(&path); }
+                        }
+                        self.playlist.set_date_filter(DateFilter::new(self.config.memories));
+                        self.caption = self.caption_info();
+                        self.show_settings = false;
+                        frame.set_decorations(false);
+                        self.scan_image_files(true);
+                        self.last_update = Instant::now();
+                    }
+                });
+            });
+        } else {
+            egui::CentralPanel::default().frame(egui::Frame::none()).show(ctx, |ui| {
+                if let Some(shown) = &self.current_image {
+                    let window = ui.available_rect_before_wrap();
+                    let incoming = self.image_layers(shown, window);
+                    match &self.transition {
+                        Some(transition) => {
+                            let settings = self.config.transition;
+                            let progress = settings.progress(transition.started.elapsed());
+                            // 背景和图片分别过渡，新图片的所有层都画在旧图片之上
+                            let mut on_top = Vec::new();
+                            for ((old_fill, old), (new_fill, new)) in self.image_layers(&transition.outgoing, window).into_iter().zip(incoming) {
+                                let (old_layers, new_layers) = settings.layers(progress, window, old, new);
+                                for layer in old_layers { paint_layer(ui.painter(), old_fill, layer); }
+                                on_top.extend(new_layers.into_iter().map(|layer| (new_fill, layer)));
+                            }
+                            for (fill, layer) in on_top { paint_layer(ui.painter(), fill, layer); }
+                        }
+                        None => for (fill, layer) in incoming { paint_layer(ui.painter(), fill, layer); },
+                    }
+                    let can_adjust_crop = self.config.fit_mode == FitMode::Cover && shown.motion.is_none();
+                    let image_response = ui.allocate_rect(window, if self.adjusting_crop { Sense::click_and_drag() } else { Sense::click() });
+
+                    if self.adjusting_crop {
+                        if image_response.dragged() { self.drag_focus(image_response.drag_delta(), window.size()); }
+                        egui::Area::new("crop_bar_area").anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -5.0)).show(ctx, |ui| {
+                            let bar_frame = Frame::none().rounding(5.0).inner_margin(egui::style::Margin::symmetric(10.0, 5.0)).fill(Color32::from_rgba_unmultiplied(30, 30, 30, 200));
+                            bar_frame.show(ui, |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.label(egui::RichText::new("Drag to move the crop").color(Color32::WHITE));
+                                    let focus = self.current_image.as_ref().and_then(|shown| shown.focus);
+                                    if ui.button("Done").clicked() { self.end_crop_adjustment(Some(focus)); }
+                                    if ui.button("Automatic").on_hover_text("Use the detected focus point, or the center").clicked() { self.end_crop_adjustment(Some(None)); }
+                                    if ui.button("Cancel").clicked() { self.end_crop_adjustment(None); }
+                                });
+                            });
+                        });
+                    } else if image_response.clicked() { self.show_next(); }
+
+                    let mut drag_handle_response: Option<egui::Response> = None;
+                    if self.show_drag_bar {
+                        egui::Area::new("drag_bar_area").anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 5.0)).show(ctx, |ui| {
+                            let bar_frame = Frame::none().rounding(5.0).inner_margin(egui::style::Margin::symmetric(10.0, 5.0)).fill(Color32::from_rgba_unmultiplied(30, 30, 30, 200));
+                            bar_frame.show(ui, |ui| {
+                                ui.label(egui::RichText::new("Drag to move").color(Color32::WHITE));
+                                let response = ui.interact(ui.max_rect(), ui.id().with("drag_handle"), Sense::drag());
+                                if response.dragged() { frame.drag_window(); }
+
+                                // --- 新增：当拖动结束后，立即保存窗口位置 ---
+                                if response.drag_released() && let Some(pos) = frame.info().window_info.position {
+                                    self.config.window_pos = Some((pos.x, pos.y));
+                                    save_config(&self.config);
+                                }
+
+                                drag_handle_response = Some(response);
+                            });
+                        });
+                    }
+
+                    let is_pointer_over_ui = image_response.hovered() || drag_handle_response.as_ref().is_some_and(|r| r.hovered());
+
+                    if is_pointer_over_ui { self.show_drag_bar = true; self.hover_leave_time = None; }
+                    else {
+                        if self.hover_leave_time.is_none() { self.hover_leave_time = Some(Instant::now()); }
+                        if let Some(leave_time) = self.hover_leave_time && leave_time.elapsed() > Duration::from_millis(100) { self.show_drag_bar = false; }
+                    }
+
+                    if image_response.hovered() {
+                        egui::Area::new("tooltip_area").anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(5.0, -5.0)).show(ctx, |ui| {
+                            let tooltip_frame = Frame::none().rounding(3.0).inner_margin(egui::style::Margin::symmetric(4.0, 2.0)).fill(Color32::from_rgba_unmultiplied(20, 20, 20, 180));
+                            tooltip_frame.show(ui, |ui| { ui.label(egui::RichText::new("Left-click: Next | Scroll or ←/→: Back/Next | Right-click: Menu").color(Color32::LIGHT_GRAY).small()); });
+                        });
+                    }
+
+                    if let Some(caption) = &self.caption {
+                        let settings = &self.config.caption;
+                        let opacity = match settings.visibility {
+                            CaptionVisibility::Always => 1.0,
+                            CaptionVisibility::OnHover => ctx.animate_bool_with_time(egui::Id::new("caption_fade"), is_pointer_over_ui, 0.3),
+                        };
+                        let text = caption.render(&settings.template);
+                        if opacity > 0.0 && !text.is_empty() {
+                            let (align, mut offset) = settings.position.anchor();
+                            // 悬停提示在底部，标题让到它上面
+                            if image_response.hovered() && offset.y < 0.0 { offset.y -= 22.0; }
+                            egui::Area::new("caption_area").anchor(align, offset).interactable(false).show(ctx, |ui| {
+                                let caption_frame = Frame::none().rounding(3.0).inner_margin(egui::style::Margin::symmetric(6.0, 3.0)).fill(Color32::from_rgba_unmultiplied(20, 20, 20, 160).gamma_multiply(opacity));
+                                caption_frame.show(ui, |ui| { ui.label(egui::RichText::new(text).size(settings.font_size).color(Color32::WHITE.gamma_multiply(opacity))); });
+                            });
+                        }
+                    }
+
+                    image_response.context_menu(|ui| {
+                        let mut rating = self.current_image_path.as_deref().map(|path| self.ratings.get(path)).unwrap_or_default();
+                        if ui.checkbox(&mut rating.favorite, "Favorite").changed() { self.rate_current_image(|r| r.favorite = rating.favorite); ui.close_menu(); }
+                        ui.horizontal(|ui| {
+                            ui.label("Rating:");
+                            for stars in 0..=MAX_STARS {
+                                let label = if stars == 0 { "–".to_string() } else { "★".repeat(usize::from(stars)) };
+                                if ui.selectable_label(rating.stars == stars, label).clicked() { self.rate_current_image(|r| r.stars = stars); ui.close_menu(); }
+                            }
+                        });
+                        if ui.button("Hide (never show again)").clicked() { self.rate_current_image(|r| r.hidden = true); ui.close_menu(); }
+                        ui.separator();
+                        if ui.add_enabled(can_adjust_crop, egui::Button::new("Adjust Crop")).on_disabled_hover_text("Cover mode without the Ken Burns effect only").clicked() { self.adjusting_crop = true; ui.close_menu(); }
+                        if ui.button("Settings").clicked() { self.show_settings = true; frame.set_decorations(true); ui.close_menu(); }
+                    });
+                } else {
+                    ui.label("No images found. Please add a folder in the settings.");
+                    if ui.button("Open Settings").clicked() { self.show_settings = true; frame.set_decorations(true); }
+                }
+            });
+        }
+        if self.transition.is_some() { ctx.request_repaint(); }
+        // Ken Burns 移动很慢，30 fps 足够
+        if self.current_image.as_ref().is_some_and(|shown| shown.motion.is_some()) { ctx.request_repaint_after(Duration::from_millis(33)); }
+        let next_frame = self.current_image.as_ref().and_then(ShownImage::until_next_frame);
+        ctx.request_repaint_after(next_frame.map_or(Duration::from_millis(50), |due| due.min(Duration::from_millis(50))));
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // 文件监视产生的索引变化只在扫描结束时保存，退出前补存一次
+        self.index.lock().unwrap().save(Path::new(INDEX_FILE));
+        self.history.save(Path::new(HISTORY_FILE));
+    }
+}
+
+/// Paints a layer at its opacity.
+fn paint_layer(painter: &egui::Painter, fill: Fill<'_>, layer: Layer) {
+    match fill {
+        Fill::Texture(texture) => painter.image(texture.id(), layer.rect, layer.uv, Color32::WHITE.gamma_multiply(layer.opacity)),
+        Fill::Color(color) => painter.rect_filled(layer.rect, 0.0, color.gamma_multiply(layer.opacity)),
+    };
+}
+
+/// A checkbox that turns an optional limit on (starting at `default`) or off,
+/// followed by its value.
+fn optional_value<T: egui::emath::Numeric>(ui: &mut egui::Ui, value: &mut Option<T>, label: &str, default: T, range: RangeInclusive<T>) {
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        if ui.checkbox(&mut enabled, label).changed() { *value = enabled.then_some(default); }
+        if let Some(value) = value { ui.add(egui::DragValue::new(value).speed(default.to_f64() * 0.02).clamp_range(range)); }
+    });
+}
+
+/// 动态加载系统字体，并把窗口背景设置为透明
+pub fn setup_context(ctx: &egui::Context) {
+    let mut fonts = FontDefinitions::default();
+
+    // 初始化系统字体数据库
+    let mut font_database = fontdb::Database::new();
+    font_database.load_system_fonts();
+
+    // 定义一个优先字体列表，以最好地覆盖中、日、韩和西文字符
+    // 这个列表的顺序很重要，egui会依次尝试用它们来渲染字符
+    let font_families = [
+        // Windows 平台常用字体
+        "Microsoft YaHei UI",    // 简体中文
+        "Yu Gothic UI",          // 日语
+        "Malgun Gothic",         // 韩语
+        "Segoe UI",             // 英文 UI
+        // macOS 平台常用字体
+        "PingFang SC",           // 简体中文
+        "Hiragino Kaku Gothic ProN", // 日语
+        "Apple SD Gothic Neo",   // 韩语
+        "San Francisco",         // 英文 UI
+        // Linux/通用 平台常用字体
+        "Noto Sans CJK SC",      // 简体中文
+        "Noto Sans CJK JP",      // 日语
+        "Noto Sans CJK KR",      // 韩语
+        // 通用后备字体
+        "Arial",
+    ];
+
+    let mut loaded_font_names = Vec::new();
+
+    for family_name in &font_families {
+        let query = fontdb::Query {
+            families: &[fontdb::Family::Name(family_name)],
+            ..Default::default()
+        };
+
+        // 在数据库中查找字体，获取字体的源文件路径
+        if let Some(font_id) = font_database.query(&query)
+            && let Some((fontdb::Source::File(path), _index)) = font_database.face_source(font_id)
+            // 读取字体文件数据
+            && let Ok(font_data) = fs::read(path)
+        {
+            let font_name = family_name.to_lowercase().replace(' ', "-");
+
+            // 避免重复加载同一个字体文件
+            if !fonts.font_data.contains_key(&font_name) {
+                fonts.font_data.insert(
+                    font_name.clone(),
+                    FontData::from_owned(font_data),
+                );
+                loaded_font_names.push(font_name);
+            }
+        }
+    }
+
+    // 如果成功加载了任何字体，将它们设置为默认的后备字体列表
+    if !loaded_font_names.is_empty() {
+        fonts.families.insert(FontFamily::Proportional, loaded_font_names.clone());
+        fonts.families.insert(FontFamily::Monospace, loaded_font_names);
+    }
+
+    // 将配置好的字体应用到 egui 上下文
+    ctx.set_fonts(fonts);
+
+    // 设置窗口为透明背景
+    let mut visuals = Visuals::dark();
+    visuals.window_fill = Color32::TRANSPARENT;
+    visuals.window_stroke.color = Color32::TRANSPARENT;
+    ctx.set_visuals(visuals);
+}
@@ -0,0 +1,106 @@
+//! Recently shown and prefetched images, kept around so switching is instant.
+//!
+//! The cache is generic over what it stores (textures in the app) and only
+//! needs to be told how many bytes each entry costs; the least recently used
+//! entries are dropped once the configured budget is exceeded.
+
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Prefetch and cache settings (`cache` in the config).
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct CacheSettings {
+    /// How many of the upcoming images are decoded in advance.
+    pub prefetch_count: usize,
+    /// Upper bound for cached textures, in MiB.
+    pub memory_budget_mb: usize,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self { prefetch_count: 2, memory_budget_mb: 256 }
+    }
+}
+
+impl CacheSettings {
+    pub fn budget_bytes(&self) -> usize { self.memory_budget_mb.saturating_mul(1024 * 1024) }
+}
+
+/// Least-recently-used cache keyed by image path.
+#[derive(Debug)]
+pub struct ImageCache<V> {
+    // 最近使用的在末尾
+    entries: VecDeque<(PathBuf, V, usize)>,
+    used: usize,
+    budget: usize,
+}
+
+impl<V> ImageCache<V> {
+    pub fn new(budget: usize) -> Self {
+        Self { entries: VecDeque::new(), used: 0, budget }
+    }
+
+    pub fn len(&self) -> usize { self.entries.len() }
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+    pub fn used_bytes(&self) -> usize { self.used }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.entries.iter().any(|(p, _, _)| p == path)
+    }
+
+    /// Looks up an entry and marks it as the most recently used.
+    pub fn get(&mut self, path: &Path) -> Option<&V> {
+        let position = self.entries.iter().position(|(p, _, _)| p == path)?;
+        let entry = self.entries.remove(position)?;
+        self.entries.push_back(entry);
+        self.entries.back().map(|(_, value, _)| value)
+    }
+
+    /// Adds or replaces an entry and evicts the oldest ones until the cache
+    /// fits its budget again. The new entry itself is always kept, even when
+    /// it alone is larger than the budget.
+    pub fn insert(&mut self, path: PathBuf, value: V, bytes: usize) {
+        self.remove(&path);
+        self.used += bytes;
+        self.entries.push_back((path, value, bytes));
+        self.evict();
+    }
+
+    pub fn remove(&mut self, path: &Path) -> Option<V> {
+        let position = self.entries.iter().position(|(p, _, _)| p == path)?;
+        let (_, value, bytes) = self.entries.remove(position)?;
+        self.used -= bytes;
+        Some(value)
+    }
+
+    /// Drops every entry for `path` or a file below it (a removed directory).
+    pub fn remove_under(&mut self, path: &Path) {
+        let used = &mut self.used;
+        self.entries.retain(|(p, _, bytes)| {
+            let keep = !p.starts_with(path);
+            if !keep { *used -= bytes; }
+            keep
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.used = 0;
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.used > self.budget && self.entries.len() > 1 {
+            if let Some((_, _, bytes)) = self.entries.pop_front() { self.used -= bytes; }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! The caption drawn over the photo, built from a template such as
+//! `{filename} · {date}`.
+
+use std::path::Path;
+
+use egui::{vec2, Align2, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{exif_data::ExifData, index::IndexEntry, memories::Day};
+
+/// Every placeholder a template can use, for the settings panel.
+pub const PLACEHOLDERS: [&str; 11] =
+    ["{filename}", "{folder}", "{path}", "{date}", "{camera}", "{lens}", "{exposure}", "{size}", "{index}", "{total}", "{album}"];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CaptionPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomCenter,
+    #[default]
+    BottomRight,
+}
+
+impl CaptionPosition {
+    /// Where the caption is anchored in the window, and its distance from the edges.
+    pub fn anchor(self) -> (Align2, Vec2) {
+        match self {
+            CaptionPosition::TopLeft => (Align2::LEFT_TOP, vec2(5.0, 5.0)),
+            CaptionPosition::TopRight => (Align2::RIGHT_TOP, vec2(-5.0, 5.0)),
+            CaptionPosition::BottomLeft => (Align2::LEFT_BOTTOM, vec2(5.0, -5.0)),
+            CaptionPosition::BottomCenter => (Align2::CENTER_BOTTOM, vec2(0.0, -5.0)),
+            CaptionPosition::BottomRight => (Align2::RIGHT_BOTTOM, vec2(-5.0, -5.0)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CaptionVisibility {
+    Always,
+    /// Fades in while the pointer is over the photo.
+    #[default]
+    OnHover,
+}
+
+/// Caption settings (`caption` in the config).
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct CaptionSettings {
+    pub enabled: bool,
+    /// Text with placeholders; see [`PLACEHOLDERS`]. Lines whose
+    /// placeholders are all empty are left out.
+    pub template: String,
+    pub position: CaptionPosition,
+    pub font_size: f32,
+    pub visibility: CaptionVisibility,
+}
+
+impl Default for CaptionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            template: "{filename}\n{date} · {camera}".to_string(),
+            position: CaptionPosition::BottomRight,
+            font_size: 13.0,
+            visibility: CaptionVisibility::OnHover,
+        }
+    }
+}
+
+/// What the placeholders stand for, for one photo.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct CaptionInfo {
+    pub filename: String,
+    pub folder: String,
+    pub path: String,
+    /// Capture date and time, or the file date when the photo has none.
+    pub date: String,
+    pub camera: String,
+    pub lens: String,
+    pub exposure: String,
+    pub size: String,
+    pub index: String,
+    pub total: String,
+    pub album: String,
+}
+
+impl CaptionInfo {
+    /// Collects the values for `path`. `position` is its 1-based place in
+    /// the playlist and the playlist length; `utc_offset` turns the file
+    /// date into local time.
+    pub fn new(path: &Path, entry: Option<&IndexEntry>, exif: Option<&ExifData>, position: Option<(usize, usize)>, album: &str, utc_offset: i64) -> Self {
+        let name = |part: Option<&std::ffi::OsStr>| part.map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+        let date = match exif.and_then(|exif| exif.capture_date).or(entry.and_then(|entry| entry.capture_date)) {
+            Some(date) => format!("{}-{:02}-{:02} {:02}:{:02}", date.year, date.month, date.day, date.hour, date.minute),
+            None => entry.map_or_else(String::new, |entry| {
+                let day = Day::from_unix_seconds((entry.mtime / 1000) as i64, utc_offset);
+                format!("{}-{:02}-{:02}", day.year, day.month, day.day)
+            }),
+        };
+        let exif_text = |field: fn(&ExifData) -> &Option<String>| exif.and_then(|exif| field(exif).clone()).unwrap_or_default();
+        Self {
+            filename: name(path.file_name()),
+            folder: name(path.parent().and_then(Path::file_name)),
+            path: path.display().to_string(),
+            date,
+            camera: exif_text(|exif| &exif.camera),
+            lens: exif_text(|exif| &exif.lens),
+            exposure: exif_text(|exif| &exif.exposure),
+            size: entry.map_or_else(String::new, |entry| { let (width, height) = entry.display_size(); format!("{width}×{height}") }),
+            index: position.map_or_else(String::new, |(index, _)| index.to_string()),
+            total: position.map_or_else(String::new, |(_, total)| total.to_string()),
+            album: album.to_string(),
+        }
+    }
+
+    fn value(&self, placeholder: &str) -> Option<&str> {
+        Some(match placeholder {
+            "filename" => &self.filename,
+            "folder" => &self.folder,
+            "path" => &self.path,
+            "date" => &self.date,
+            "camera" => &self.camera,
+            "lens" => &self.lens,
+            "exposure" => &self.exposure,
+            "size" => &self.size,
+            "index" => &self.index,
+            "total" => &self.total,
+            "album" => &self.album,
+            _ => return None,
+        })
+    }
+
+    /// Fills in `template`. Unknown placeholders stay as they are; lines
+    /// that only had empty placeholders are dropped, and so is the separator
+    /// next to an empty placeholder. Filled-in values are never trimmed.
+    pub fn render(&self, template: &str) -> String {
+        // 空的占位符会留下多余的分隔符，例如 "2021-06-15 · "
+        let separator = |c: char| c.is_whitespace() || "·|,-–/".contains(c);
+        let mut lines = Vec::new();
+        for line in template.lines() {
+            let (mut text, mut had_placeholder, mut had_value) = (String::new(), false, false);
+            // 模板文字从 literal_start 开始，之前是填进去的值，不能裁剪
+            let (mut literal_start, mut skip_separator) = (0, false);
+            let mut rest = line;
+            loop {
+                let start = rest.find('{');
+                let literal = &rest[..start.unwrap_or(rest.len())];
+                text.push_str(if std::mem::take(&mut skip_separator) { literal.trim_start_matches(separator) } else { literal });
+                let Some(start) = start else { break };
+                let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+                    text.push_str(&rest[start..]);
+                    break;
+                };
+                match self.value(&rest[start + 1..end]) {
+                    Some("") => {
+                        had_placeholder = true;
+                        // 去掉前面的分隔符；前面没有时去掉后面的
+                        let kept = literal_start + text[literal_start..].trim_end_matches(separator).len();
+                        if kept < text.len() { text.truncate(kept); } else { skip_separator = true; }
+                    }
+                    Some(value) => {
+                        had_placeholder = true;
+                        had_value = true;
+                        text.push_str(value);
+                        literal_start = text.len();
+                    }
+                    None => text.push_str(&rest[start..=end]),
+                }
+                rest = &rest[end + 1..];
+            }
+            if had_placeholder && !had_value {
+                continue;
+            }
+            lines.push(text);
+        }
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,421 @@
+//! Persistent user settings (`photo_widget_config.json`).
+
+use std::{fs, path::Path, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{animation::AnimationSettings, cache::CacheSettings, caption::CaptionSettings, duplicates::DuplicateSettings, formats::FormatSettings, ken_burns::KenBurnsSettings, library::ImageLimits, memories::MemorySettings, transition::TransitionSettings};
+
+pub const CONFIG_FILE: &str = "photo_widget_config.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum ResizeAnchor {
+    Center,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum TimeUnit {
+    Seconds,
+    Minutes,
+    Hours,
+}
+
+impl TimeUnit {
+    pub fn seconds(self) -> u64 {
+        match self {
+            TimeUnit::Seconds => 1,
+            TimeUnit::Minutes => 60,
+            TimeUnit::Hours => 3600,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum ImageOrientationFilter {
+    #[default]
+    Both,
+    Landscape,
+    Portrait,
+    Square,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum FitMode {
+    Cover,
+    Contain,
+    /// Keep the preset size and show the whole image centered in it, with
+    /// the bars around it filled.
+    Letterbox,
+}
+
+/// What fills the bars around an image in `FitMode::Letterbox`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LetterboxFill {
+    /// A blurred, darkened copy of the image, scaled up to fill the window.
+    #[default]
+    Blurred,
+    /// `letterbox_color`.
+    Color,
+}
+
+/// Order in which photos are shown.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PlaybackOrder {
+    /// Every photo once in random order, then reshuffle.
+    #[default]
+    Shuffle,
+    /// Pick any photo each time; repeats are possible.
+    Random,
+    /// Alphabetically by file name, across all folders.
+    ByName,
+    /// Oldest modification time first.
+    ByModified,
+    /// Oldest EXIF capture date first; photos without one come last.
+    ByCaptureDate,
+    /// Folder by folder, by file name within each folder.
+    ByFolder,
+}
+
+impl PlaybackOrder {
+    /// Sequential orders continue where the previous session stopped.
+    pub fn is_sequential(self) -> bool {
+        !matches!(self, PlaybackOrder::Shuffle | PlaybackOrder::Random)
+    }
+}
+
+/// Filter used to scale photos down to the widget size.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum ResampleFilter {
+    /// Fastest, blocky.
+    Nearest,
+    /// Bilinear.
+    Triangle,
+    /// Bicubic; sharp and still reasonably fast.
+    #[default]
+    CatmullRom,
+    /// Best quality, slowest.
+    Lanczos3,
+}
+
+/// How the configured folders are watched for changes while the widget runs.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum WatchMode {
+    /// Only pick up changes on the next rescan.
+    #[default]
+    Off,
+    /// OS change notifications (inotify on Linux), polling if unavailable.
+    Native,
+    /// Periodic polling; needed for network shares, which do not deliver notifications.
+    Polling,
+}
+
+/// A configured image folder (`folders` in the config).
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(from = "FolderEntryRepr")]
+pub struct FolderEntry {
+    pub path: PathBuf,
+    /// Relative share of the shown photos, independent of how many photos
+    /// the folder holds (random orders only).
+    pub weight: f32,
+    /// Disabled folders stay in the list but are not scanned.
+    pub enabled: bool,
+    /// Glob patterns a file must match to be shown; empty shows every
+    /// image. Patterns without a `/` match file names, the others the path
+    /// relative to the folder.
+    pub include: Vec<String>,
+    /// Glob patterns for files and subfolders to skip, matched like `include`.
+    pub exclude: Vec<String>,
+    /// How many levels of subfolders are scanned; `None` for all of them,
+    /// `Some(0)` for only the folder itself.
+    pub max_depth: Option<usize>,
+    /// Also scan hidden files and folders (names starting with a dot).
+    pub include_hidden: bool,
+    pub follow_symlinks: bool,
+}
+
+impl FolderEntry {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            weight: 1.0,
+            enabled: true,
+            include: Vec::new(),
+            exclude: default_exclude(),
+            max_depth: None,
+            include_hidden: false,
+            follow_symlinks: false,
+        }
+    }
+}
+
+// 旧版本的配置文件中 folders 只是路径列表
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FolderEntryRepr {
+    Path(PathBuf),
+    Entry {
+        path: PathBuf,
+        #[serde(default = "default_weight")]
+        weight: f32,
+        #[serde(default = "default_enabled")]
+        enabled: bool,
+        #[serde(default)]
+        include: Vec<String>,
+        #[serde(default = "default_exclude")]
+        exclude: Vec<String>,
+        #[serde(default)]
+        max_depth: Option<usize>,
+        #[serde(default)]
+        include_hidden: bool,
+        #[serde(default)]
+        follow_symlinks: bool,
+    },
+}
+
+fn default_weight() -> f32 { 1.0 }
+fn default_enabled() -> bool { true }
+// NAS 和相机软件生成的缩略图目录 (隐藏目录默认已跳过)
+fn default_exclude() -> Vec<String> { vec!["@eaDir".to_string(), "Thumbs".to_string()] }
+
+impl From<FolderEntryRepr> for FolderEntry {
+    fn from(repr: FolderEntryRepr) -> Self {
+        match repr {
+            FolderEntryRepr::Path(path) => Self::new(path),
+            FolderEntryRepr::Entry { path, weight, enabled, include, exclude, max_depth, include_hidden, follow_symlinks } => {
+                Self { path, weight, enabled, include, exclude, max_depth, include_hidden, follow_symlinks }
+            }
+        }
+    }
+}
+
+/// A named set of folders with its own filters and playback order. The
+/// active album lives in the top-level fields of [`AppConfig`], the others
+/// wait in [`AppConfig::albums`].
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct Album {
+    pub name: String,
+    pub folders: Vec<FolderEntry>,
+    pub orientation_filter: ImageOrientationFilter,
+    pub image_limits: ImageLimits,
+    pub memories: MemorySettings,
+    pub order: PlaybackOrder,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AppConfig {
+    pub folders: Vec<FolderEntry>,
+    pub always_on_top: bool,
+    pub refresh_interval: u64,
+    pub refresh_value: u64,
+    pub refresh_unit: TimeUnit,
+    pub landscape_width: f32,
+    pub landscape_height: f32,
+    pub portrait_width: f32,
+    pub portrait_height: f32,
+    pub fit_mode: FitMode,
+    pub resize_anchor: ResizeAnchor,
+    pub orientation_filter: ImageOrientationFilter,
+    #[serde(default)]
+    pub image_limits: ImageLimits,
+    // --- 新增字段：用于存储窗口位置 ---
+    // serde(default) 确保在旧的配置文件中没有此字段时程序不会崩溃
+    #[serde(default)]
+    pub window_pos: Option<(f32, f32)>,
+    #[serde(default)]
+    pub watch_mode: WatchMode,
+    #[serde(default)]
+    pub formats: FormatSettings,
+    #[serde(default)]
+    pub animation: AnimationSettings,
+    #[serde(default)]
+    pub resample_filter: ResampleFilter,
+    /// Detect where the subject of each photo is while indexing, so `Cover`
+    /// crops around it. A focus point set by hand is used either way.
+    #[serde(default)]
+    pub smart_crop: bool,
+    #[serde(default)]
+    pub letterbox_fill: LetterboxFill,
+    /// RGB color of the bars with `LetterboxFill::Color`.
+    #[serde(default)]
+    pub letterbox_color: [u8; 3],
+    #[serde(default)]
+    pub transition: TransitionSettings,
+    #[serde(default)]
+    pub ken_burns: KenBurnsSettings,
+    #[serde(default)]
+    pub caption: CaptionSettings,
+    #[serde(default)]
+    pub cache: CacheSettings,
+    /// How many shown photos back/forward navigation remembers.
+    #[serde(default = "default_history_length")]
+    pub history_length: usize,
+    #[serde(default)]
+    pub order: PlaybackOrder,
+    /// Show favorites and images with more stars more often (random orders only).
+    #[serde(default)]
+    pub favor_ratings: bool,
+    #[serde(default)]
+    pub memories: MemorySettings,
+    #[serde(default)]
+    pub duplicates: DuplicateSettings,
+    /// Name of the album in `folders`, `orientation_filter`, ... above.
+    #[serde(default = "default_album_name")]
+    pub active_album: String,
+    /// The albums that are not active.
+    #[serde(default)]
+    pub albums: Vec<Album>,
+}
+
+fn default_history_length() -> usize { 100 }
+fn default_album_name() -> String { "Default".to_string() }
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            folders: vec![],
+            always_on_top: false,
+            refresh_interval: 300,
+            refresh_value: 5,
+            refresh_unit: TimeUnit::Minutes,
+            landscape_width: 400.0,
+            landscape_height: 300.0,
+            portrait_width: 300.0,
+            portrait_height: 400.0,
+            fit_mode: FitMode::Cover,
+            resize_anchor: ResizeAnchor::Center,
+            orientation_filter: ImageOrientationFilter::Both,
+            image_limits: ImageLimits::default(),
+            // --- 初始化新增的字段 ---
+            window_pos: None,
+            watch_mode: WatchMode::Off,
+            formats: FormatSettings::default(),
+            animation: AnimationSettings::default(),
+            resample_filter: ResampleFilter::CatmullRom,
+            smart_crop: false,
+            letterbox_fill: LetterboxFill::Blurred,
+            letterbox_color: [0, 0, 0],
+            transition: TransitionSettings::default(),
+            ken_burns: KenBurnsSettings::default(),
+            caption: CaptionSettings::default(),
+            cache: CacheSettings::default(),
+            history_length: default_history_length(),
+            order: PlaybackOrder::Shuffle,
+            favor_ratings: false,
+            memories: MemorySettings::default(),
+            duplicates: DuplicateSettings::default(),
+            active_album: default_album_name(),
+            albums: Vec::new(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Paths of the folders that should be scanned and watched.
+    pub fn enabled_folders(&self) -> Vec<PathBuf> {
+        self.folders.iter().filter(|folder| folder.enabled).map(|folder| folder.path.clone()).collect()
+    }
+
+    /// `(path, weight)` of the enabled folders, for the playlist.
+    pub fn folder_weights(&self) -> Vec<(PathBuf, f32)> {
+        self.folders.iter().filter(|folder| folder.enabled).map(|folder| (folder.path.clone(), folder.weight)).collect()
+    }
+
+    /// Names of all albums, the active one included, sorted.
+    pub fn album_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.albums.iter().map(|album| album.name.clone()).chain([self.active_album.clone()]).collect();
+        names.sort_by_key(|name| name.to_lowercase());
+        names
+    }
+
+    /// Makes the album called `name` active, parking the current one.
+    /// Returns `false` if there is no such (inactive) album.
+    pub fn switch_album(&mut self, name: &str) -> bool {
+        let Some(position) = self.albums.iter().position(|album| album.name == name) else { return false };
+        let album = self.albums.remove(position);
+        let current = self.take_active_album();
+        self.albums.push(current);
+        self.set_active_album(album);
+        true
+    }
+
+    /// Parks the current album and starts an empty one called `name`.
+    /// Returns `false` if the name is empty or taken.
+    pub fn add_album(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        if name.is_empty() || self.album_names().iter().any(|existing| existing == name) {
+            return false;
+        }
+        let current = self.take_active_album();
+        self.albums.push(current);
+        self.set_active_album(Album { name: name.to_string(), ..Album::default() });
+        true
+    }
+
+    pub fn rename_album(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        if name.is_empty() || self.album_names().iter().any(|existing| existing == name) {
+            return false;
+        }
+        self.active_album = name.to_string();
+        true
+    }
+
+    /// Deletes the active album and switches to the first remaining one.
+    /// The last album cannot be deleted.
+    pub fn remove_album(&mut self) -> bool {
+        if self.albums.is_empty() {
+            return false;
+        }
+        let next = self.albums.remove(0);
+        self.set_active_album(next);
+        true
+    }
+
+    fn take_active_album(&mut self) -> Album {
+        Album {
+            name: std::mem::take(&mut self.active_album),
+            folders: std::mem::take(&mut self.folders),
+            orientation_filter: self.orientation_filter,
+            image_limits: self.image_limits,
+            memories: self.memories,
+            order: self.order,
+        }
+    }
+
+    fn set_active_album(&mut self, album: Album) {
+        self.active_album = album.name;
+        self.folders = album.folders;
+        self.orientation_filter = album.orientation_filter;
+        self.image_limits = album.image_limits;
+        self.memories = album.memories;
+        self.order = album.order;
+    }
+
+    /// Derives the value/unit pair shown in the settings panel from
+    /// `refresh_interval`, picking the largest unit that divides it evenly.
+    pub fn sync_refresh_unit(&mut self) {
+        let interval = self.refresh_interval;
+        if interval > 0 {
+            if interval.is_multiple_of(3600) { self.refresh_unit = TimeUnit::Hours; self.refresh_value = interval / 3600; }
+            else if interval.is_multiple_of(60) { self.refresh_unit = TimeUnit::Minutes; self.refresh_value = interval / 60; }
+            else { self.refresh_unit = TimeUnit::Seconds; self.refresh_value = interval; }
+        }
+    }
+
+    /// Inverse of [`AppConfig::sync_refresh_unit`]: recomputes `refresh_interval`
+    /// (in seconds) from what the user entered.
+    pub fn apply_refresh_value(&mut self) {
+        self.refresh_interval = self.refresh_value * self.refresh_unit.seconds();
+    }
+}
+
+pub fn save_config(config: &AppConfig) { save_config_to(Path::new(CONFIG_FILE), config) }
+pub fn load_config() -> Result<AppConfig, Box<dyn std::error::Error>> { load_config_from(Path::new(CONFIG_FILE)) }
+
+pub fn save_config_to(path: &Path, config: &AppConfig) { if let Ok(json) = serde_json::to_string_pretty(config) { let _ = fs::write(path, json); } }
+pub fn load_config_from(path: &Path) -> Result<AppConfig, Box<dyn std::error::Error>> { let json_str = fs::read_to_string(path)?; let config = serde_json::from_str(&json_str)?; Ok(config) }
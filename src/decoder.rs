@@ -0,0 +1,131 @@
+//! A fixed set of worker threads that decode images for display.
+//!
+//! Every request gets a [`RequestId`]; the UI thread matches results to the
+//! request it is still waiting for and cancels the ones it no longer needs
+//! (the user clicked on, the prefetch window moved). Cancelled requests that
+//! have not started are skipped, and results of ones that were already
+//! running are dropped instead of being delivered.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+};
+
+use crate::render::{self, DecodeOptions, DecodedImage};
+
+pub type RequestId = u64;
+
+pub struct DecodeResult {
+    pub id: RequestId,
+    pub path: PathBuf,
+    /// `None` when the file could not be decoded.
+    pub image: Option<DecodedImage>,
+}
+
+struct Job {
+    id: RequestId,
+    path: PathBuf,
+    options: DecodeOptions,
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: VecDeque<Job>,
+    // 已请求但还没交付也没取消的请求
+    live: HashSet<RequestId>,
+    shutdown: bool,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    available: Condvar,
+}
+
+pub struct DecodePool {
+    shared: Arc<Shared>,
+    result_rx: Receiver<DecodeResult>,
+    next_id: RequestId,
+}
+
+/// Worker count used by the app: enough to decode the current image and a
+/// prefetch in parallel without starving the rest of the machine.
+pub fn default_threads() -> usize {
+    thread::available_parallelism().map_or(2, |n| n.get().clamp(1, 4))
+}
+
+impl DecodePool {
+    pub fn new(threads: usize) -> Self {
+        let shared = Arc::new(Shared { queue: Mutex::new(Queue::default()), available: Condvar::new() });
+        let (result_tx, result_rx) = mpsc::channel();
+        for _ in 0..threads.max(1) {
+            let shared = shared.clone();
+            let result_tx = result_tx.clone();
+            thread::spawn(move || worker(&shared, &result_tx));
+        }
+        Self { shared, result_rx, next_id: 0 }
+    }
+
+    /// Queues a decode. `urgent` requests (the image the user is waiting
+    /// for) go ahead of everything already queued.
+    pub fn request(&mut self, path: PathBuf, options: DecodeOptions, urgent: bool) -> RequestId {
+        self.next_id += 1;
+        let job = Job { id: self.next_id, path, options };
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.live.insert(job.id);
+        if urgent { queue.jobs.push_front(job); } else { queue.jobs.push_back(job); }
+        drop(queue);
+        self.shared.available.notify_one();
+        self.next_id
+    }
+
+    /// Withdraws a request. Its result will not be delivered, even if a
+    /// worker is already decoding it.
+    pub fn cancel(&self, id: RequestId) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        queue.live.remove(&id);
+        queue.jobs.retain(|job| job.id != id);
+    }
+
+    /// Requests that are queued or being decoded.
+    pub fn pending(&self) -> usize {
+        self.shared.queue.lock().unwrap().live.len()
+    }
+
+    /// Results that arrived since the last call. Never blocks.
+    pub fn poll(&self) -> Vec<DecodeResult> {
+        let results: Vec<DecodeResult> = self.result_rx.try_iter().collect();
+        if results.is_empty() {
+            return results;
+        }
+        let mut queue = self.shared.queue.lock().unwrap();
+        results.into_iter().filter(|result| queue.live.remove(&result.id)).collect()
+    }
+}
+
+impl Drop for DecodePool {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().shutdown = true;
+        self.shared.available.notify_all();
+    }
+}
+
+fn worker(shared: &Shared, result_tx: &Sender<DecodeResult>) {
+    loop {
+        let job = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if queue.shutdown { return; }
+                if let Some(job) = queue.jobs.pop_front() { break job; }
+                queue = shared.available.wait(queue).unwrap();
+            }
+        };
+        let image = render::decode(&job.path, &job.options);
+        // 解码期间被取消的结果在 poll 中丢弃
+        if result_tx.send(DecodeResult { id: job.id, path: job.path, image }).is_err() { return; }
+    }
+}
@@ -0,0 +1,262 @@
+//! Finding the same photo in several places (a phone backup and an export,
+//! say) so it is shown only once per round.
+//!
+//! Exact copies share a content hash of the file bytes. Near-duplicates
+//! (re-encoded, resized) are found with a difference hash of a tiny grayscale
+//! thumbnail. Of every group only the best copy, the one with the most
+//! pixels, goes into the playlist.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use image::{imageops::FilterType, io::Reader as ImageReader};
+use serde::{Deserialize, Serialize};
+
+use crate::index::{rebase, IndexEntry};
+
+/// Duplicate detection settings (`duplicates` in the config).
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct DuplicateSettings {
+    /// Hash every image while indexing and show each photo only once.
+    pub enabled: bool,
+    /// Also treat visually similar images as duplicates. Needs a full
+    /// decode of every image once, so the first scan is slower.
+    pub near_duplicates: bool,
+    /// How many of the 64 perceptual hash bits may differ.
+    pub max_distance: u32,
+}
+
+impl Default for DuplicateSettings {
+    fn default() -> Self {
+        Self { enabled: false, near_duplicates: false, max_distance: 4 }
+    }
+}
+
+/// FNV-1a over the file contents. Stable across builds, unlike the std
+/// hasher, because it is stored in the index.
+pub fn content_hash(path: &Path) -> Option<u64> {
+    let mut file = File::open(path).ok()?;
+    let mut buffer = vec![0; 64 * 1024];
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    loop {
+        let read = file.read(&mut buffer).ok()?;
+        if read == 0 { return Some(hash); }
+        for byte in &buffer[..read] {
+            hash = (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Difference hash: one bit per horizontally adjacent pixel pair of a 9×8
+/// grayscale thumbnail, set when brightness increases to the right.
+pub fn perceptual_hash(path: &Path) -> Option<u64> {
+    let image = ImageReader::open(path).ok()?.with_guessed_format().ok()?.decode().ok()?;
+    // 先快速缩小，再精确缩放到 9×8
+    let small = image.thumbnail(64, 64).resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash = (hash << 1) | u64::from(small.get_pixel(x + 1, y)[0] > small.get_pixel(x, y)[0]);
+        }
+    }
+    Some(hash)
+}
+
+pub fn hash_distance(a: u64, b: u64) -> u32 { (a ^ b).count_ones() }
+
+/// What the playlist should do with a newly added image.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Admission {
+    /// Not a duplicate, or the best copy so far of a new group.
+    Show,
+    /// A worse copy of an image that is already shown.
+    Skip,
+    /// Better than the shown copy, which has to leave the playlist.
+    Replace(PathBuf),
+}
+
+#[derive(Clone, Debug)]
+struct Member {
+    path: PathBuf,
+    content: Option<u64>,
+    perceptual: Option<u64>,
+    aspect_ratio: f32,
+    // 排序用：像素越多越好，其次文件越大越好
+    quality: (u64, u64),
+}
+
+impl Member {
+    /// Whether `self` should be shown instead of `other`.
+    fn is_better_than(&self, other: &Member) -> bool {
+        (self.quality, &other.path) > (other.quality, &self.path)
+    }
+}
+
+/// Groups of duplicates among the images added so far. Only groups with at
+/// least two copies count as duplicates.
+#[derive(Default, Debug)]
+pub struct Duplicates {
+    settings: DuplicateSettings,
+    // 每组按质量从高到低排列，第一张是显示的那张
+    groups: Vec<Vec<Member>>,
+    group_of: HashMap<PathBuf, usize>,
+    by_content: HashMap<u64, usize>,
+    by_perceptual: HashTree,
+}
+
+impl Duplicates {
+    pub fn new(settings: DuplicateSettings) -> Self {
+        Self { settings, ..Self::default() }
+    }
+
+    pub fn settings(&self) -> DuplicateSettings { self.settings }
+
+    /// Records an indexed image. Images without hashes are never duplicates.
+    pub fn add(&mut self, path: PathBuf, entry: &IndexEntry) -> Admission {
+        if let Some(&group) = self.group_of.get(&path) {
+            return if self.groups[group][0].path == path { Admission::Show } else { Admission::Skip };
+        }
+        let (width, height) = entry.display_size();
+        let copy = Member {
+            path: path.clone(),
+            content: entry.content_hash,
+            perceptual: entry.perceptual_hash.filter(|_| self.settings.near_duplicates),
+            aspect_ratio: width as f32 / height.max(1) as f32,
+            quality: (u64::from(width) * u64::from(height), entry.size),
+        };
+        let Some(group) = self.find_group(&copy) else {
+            self.insert_group(copy);
+            return Admission::Show;
+        };
+        let copies = &mut self.groups[group];
+        let position = copies.iter().position(|other| copy.is_better_than(other)).unwrap_or(copies.len());
+        let shown = copies[0].path.clone();
+        if let Some(perceptual) = copy.perceptual { self.by_perceptual.insert(perceptual, group); }
+        copies.insert(position, copy);
+        self.group_of.insert(path, group);
+        if let Some(content) = entry.content_hash { self.by_content.entry(content).or_insert(group); }
+        if position == 0 { Admission::Replace(shown) } else { Admission::Skip }
+    }
+
+    /// Drops an image, or every image below a removed directory. Returns the
+    /// copies that take the place of removed shown ones.
+    pub fn remove(&mut self, path: &Path) -> Vec<PathBuf> {
+        let mut promoted = Vec::new();
+        let affected: Vec<usize> = self.group_of.iter().filter(|(p, _)| p.starts_with(path)).map(|(_, group)| *group).collect();
+        for group in affected {
+            let copies = &mut self.groups[group];
+            let Some(first) = copies.first().map(|copy| copy.path.clone()) else { continue };
+            copies.retain(|copy| !copy.path.starts_with(path));
+            if first.starts_with(path) && let Some(next) = copies.first() { promoted.push(next.path.clone()); }
+        }
+        self.group_of.retain(|p, _| !p.starts_with(path));
+        let groups = &self.groups;
+        self.by_content.retain(|_, group| !groups[*group].is_empty());
+        promoted.sort();
+        promoted.dedup();
+        promoted
+    }
+
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        for copy in self.groups.iter_mut().flatten().filter(|copy| copy.path.starts_with(from)) {
+            let renamed = rebase(&copy.path, from, to);
+            if let Some(group) = self.group_of.remove(&copy.path) { self.group_of.insert(renamed.clone(), group); }
+            copy.path = renamed;
+        }
+    }
+
+    /// Every group with more than one copy, the shown copy first.
+    pub fn groups(&self) -> Vec<Vec<PathBuf>> {
+        self.groups.iter().filter(|copies| copies.len() > 1).map(|copies| copies.iter().map(|copy| copy.path.clone()).collect()).collect()
+    }
+
+    /// How many images are not shown because a better copy is.
+    pub fn skipped(&self) -> usize {
+        self.groups.iter().map(|copies| copies.len().saturating_sub(1)).sum()
+    }
+
+    fn find_group(&self, copy: &Member) -> Option<usize> {
+        if let Some(group) = copy.content.and_then(|content| self.by_content.get(&content)) {
+            return Some(*group);
+        }
+        let perceptual = copy.perceptual?;
+        // 树里可能还留着已删除的图片，所以候选的组要逐个确认；裁剪过的图片不算重复
+        let mut candidates = self.by_perceptual.within(perceptual, self.settings.max_distance);
+        candidates.sort_unstable();
+        candidates.into_iter().find(|&group| {
+            self.groups[group].iter().any(|other| {
+                other.perceptual.is_some_and(|hash| hash_distance(hash, perceptual) <= self.settings.max_distance)
+                    && (other.aspect_ratio - copy.aspect_ratio).abs() <= 0.05 * copy.aspect_ratio
+            })
+        })
+    }
+
+    fn insert_group(&mut self, copy: Member) {
+        let group = self.groups.len();
+        if let Some(content) = copy.content { self.by_content.insert(content, group); }
+        if let Some(perceptual) = copy.perceptual { self.by_perceptual.insert(perceptual, group); }
+        self.group_of.insert(copy.path.clone(), group);
+        self.groups.push(vec![copy]);
+    }
+}
+
+/// A BK-tree of perceptual hashes, so finding the similar ones does not
+/// compare against every image.
+#[derive(Default, Debug)]
+struct HashTree {
+    nodes: Vec<HashNode>,
+}
+
+#[derive(Debug)]
+struct HashNode {
+    hash: u64,
+    groups: Vec<usize>,
+    // (与本节点的距离, 子节点)
+    children: Vec<(u32, usize)>,
+}
+
+impl HashTree {
+    fn insert(&mut self, hash: u64, group: usize) {
+        let new_node = |groups| HashNode { hash, groups, children: Vec::new() };
+        if self.nodes.is_empty() {
+            self.nodes.push(new_node(vec![group]));
+            return;
+        }
+        let mut node = 0;
+        loop {
+            let distance = hash_distance(self.nodes[node].hash, hash);
+            if distance == 0 {
+                if !self.nodes[node].groups.contains(&group) { self.nodes[node].groups.push(group); }
+                return;
+            }
+            match self.nodes[node].children.iter().find(|(d, _)| *d == distance) {
+                Some(&(_, child)) => node = child,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes.push(new_node(vec![group]));
+                    self.nodes[node].children.push((distance, child));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// The groups of every hash at most `max_distance` bits from `hash`.
+    fn within(&self, hash: u64, max_distance: u32) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut pending = if self.nodes.is_empty() { Vec::new() } else { vec![0] };
+        while let Some(node) = pending.pop() {
+            let node = &self.nodes[node];
+            let distance = hash_distance(node.hash, hash);
+            if distance <= max_distance { found.extend(&node.groups); }
+            // 三角不等式：只有这些子树里可能有足够近的哈希
+            pending.extend(node.children.iter().filter(|(d, _)| d.abs_diff(distance) <= max_distance).map(|(_, child)| *child));
+        }
+        found
+    }
+}
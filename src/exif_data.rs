@@ -0,0 +1,138 @@
+//! Metadata embedded in photo files (EXIF).
+
+use std::{fs::File, io::BufReader, path::Path};
+
+use exif::{Exif, In, Tag, Value};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+/// The EXIF fields the widget cares about.
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct ExifData {
+    /// Orientation tag (1-8), 0 when absent.
+    pub orientation: u16,
+    /// When the photo was taken (`DateTimeOriginal`, else `DateTime`).
+    pub capture_date: Option<CaptureDate>,
+    /// Camera model, with the maker in front unless the model names it.
+    pub camera: Option<String>,
+    pub lens: Option<String>,
+    /// Shutter speed, aperture, ISO and focal length, e.g. `1/250 s · f/2.8 · ISO 100 · 35 mm`.
+    pub exposure: Option<String>,
+}
+
+/// Camera-local date and time as written in EXIF (no time zone).
+/// Field order makes the derived ordering chronological.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct CaptureDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl CaptureDate {
+    /// `YYYYMMDDhhmmss` as a number, for sort keys.
+    pub fn packed(self) -> u64 {
+        [self.month, self.day, self.hour, self.minute, self.second].iter().fold(self.year as u64, |acc, part| acc * 100 + *part as u64)
+    }
+
+    fn parse(value: &Value) -> Option<Self> {
+        let Value::Ascii(parts) = value else { return None };
+        let date = exif::DateTime::from_ascii(parts.first()?).ok()?;
+        (date.year > 0 && (1..=12).contains(&date.month) && (1..=31).contains(&date.day)).then_some(Self {
+            year: date.year,
+            month: date.month,
+            day: date.day,
+            hour: date.hour,
+            minute: date.minute,
+            second: date.second,
+        })
+    }
+}
+
+impl ExifData {
+    /// Reads EXIF from a JPEG, TIFF, PNG, WebP or HEIF container.
+    /// `None` if the file has no (readable) EXIF block.
+    pub fn read(path: &Path) -> Option<Self> {
+        let file = File::open(path).ok()?;
+        let exif = exif::Reader::new().read_from_container(&mut BufReader::new(file)).ok()?;
+        let orientation = exif
+            .get_field(Tag::Orientation, In::PRIMARY)
+            .and_then(|field| field.value.get_uint(0))
+            .filter(|value| (1..=8).contains(value))
+            .unwrap_or(0) as u16;
+        let capture_date = [Tag::DateTimeOriginal, Tag::DateTime]
+            .into_iter()
+            .find_map(|tag| exif.get_field(tag, In::PRIMARY).and_then(|field| CaptureDate::parse(&field.value)));
+        Some(Self { orientation, capture_date, camera: camera(&exif), lens: text(&exif, Tag::LensModel), exposure: exposure(&exif) })
+    }
+}
+
+fn text(exif: &Exif, tag: Tag) -> Option<String> {
+    let Value::Ascii(parts) = &exif.get_field(tag, In::PRIMARY)?.value else { return None };
+    let text = String::from_utf8_lossy(parts.first()?).trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn rational(exif: &Exif, tag: Tag) -> Option<f64> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(values) => values.first().map(|value| value.to_f64()).filter(|value| value.is_finite() && *value > 0.0),
+        _ => None,
+    }
+}
+
+fn camera(exif: &Exif) -> Option<String> {
+    let model = text(exif, Tag::Model);
+    let Some(make) = text(exif, Tag::Make) else { return model };
+    let Some(model) = model else { return Some(make) };
+    // "Canon" + "Canon EOS R5" 或 "NIKON CORPORATION" + "NIKON D750"：型号里已经有厂商名
+    let brand = make.split_whitespace().next().unwrap_or_default().to_lowercase();
+    Some(if model.to_lowercase().starts_with(&brand) { model } else { format!("{make} {model}") })
+}
+
+fn exposure(exif: &Exif) -> Option<String> {
+    let trim = |value: f64| format!("{value:.1}").trim_end_matches(".0").to_string();
+    let parts: Vec<String> = [
+        rational(exif, Tag::ExposureTime).map(|time| if time < 1.0 { format!("1/{} s", (1.0 / time).round()) } else { format!("{} s", trim(time)) }),
+        rational(exif, Tag::FNumber).map(|f_number| format!("f/{}", trim(f_number))),
+        exif.get_field(Tag::PhotographicSensitivity, In::PRIMARY).and_then(|field| field.value.get_uint(0)).map(|iso| format!("ISO {iso}")),
+        rational(exif, Tag::FocalLength).map(|length| format!("{} mm", trim(length))),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+/// Whether an EXIF orientation turns the stored image by 90° (so width and
+/// height swap when displayed).
+pub fn orientation_swaps_axes(orientation: u16) -> bool {
+    matches!(orientation, 5..=8)
+}
+
+/// Transforms the stored pixels so the image is displayed upright.
+pub fn apply_orientation(image: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        // 5 = 转置 (沿主对角线翻转)，7 = 反转置
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Rotates clockwise by `quarter_turns` × 90°.
+pub fn apply_quarter_turns(image: DynamicImage, quarter_turns: u8) -> DynamicImage {
+    match quarter_turns % 4 {
+        1 => image.rotate90(),
+        2 => image.rotate180(),
+        3 => image.rotate270(),
+        _ => image,
+    }
+}
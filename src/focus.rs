@@ -0,0 +1,62 @@
+//! Smart cropping: where the interesting part of a photo is, so `Cover`
+//! can crop around it instead of the geometric center.
+//!
+//! The automatic focus point is the center of the busiest area of a small
+//! thumbnail: strong edges and saturated colors, which is where faces,
+//! people and subjects usually are, rather than sky or plain walls.
+
+use std::path::Path;
+
+use image::RgbImage;
+
+use crate::render;
+
+/// Longest side of the thumbnail the focus point is detected on.
+const ANALYSIS_SIZE: u32 = 96;
+
+/// Only the busiest fifth of the pixels counts.
+const BUSIEST_SHARE: f32 = 0.2;
+
+/// Decodes `path` upright and detects its focus point, in 0..1 coordinates
+/// of the displayed image.
+pub fn detect(path: &Path, user_rotation: u8) -> Option<(f32, f32)> {
+    let image = render::decode_image(path, user_rotation)?;
+    salient_point(&image.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE).to_rgb8())
+}
+
+/// The weighted center of the pixels with the most edge energy and color.
+/// `None` for images too small or too flat to have one.
+pub fn salient_point(image: &RgbImage) -> Option<(f32, f32)> {
+    let (width, height) = image.dimensions();
+    if width < 3 || height < 3 {
+        return None;
+    }
+    let luma = |x: u32, y: u32| {
+        let [r, g, b] = image.get_pixel(x, y).0;
+        0.299 * f32::from(r) + 0.587 * f32::from(g) + 0.114 * f32::from(b)
+    };
+    let mut energies = Vec::with_capacity(((width - 2) * (height - 2)) as usize);
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let gradient = (luma(x + 1, y) - luma(x - 1, y)).abs() + (luma(x, y + 1) - luma(x, y - 1)).abs();
+            let [r, g, b] = image.get_pixel(x, y).0;
+            let saturation = f32::from(r.max(g).max(b) - r.min(g).min(b));
+            energies.push((x, y, gradient + 0.5 * saturation));
+        }
+    }
+    let mut sorted: Vec<f32> = energies.iter().map(|(_, _, energy)| *energy).collect();
+    sorted.sort_by(f32::total_cmp);
+    let threshold = sorted[((1.0 - BUSIEST_SHARE) * (sorted.len() - 1) as f32) as usize];
+
+    let (mut total, mut sum_x, mut sum_y) = (0.0, 0.0, 0.0);
+    for (x, y, energy) in energies {
+        let weight = energy - threshold;
+        if weight > 0.0 {
+            total += weight;
+            sum_x += weight * (x as f32 + 0.5);
+            sum_y += weight * (y as f32 + 0.5);
+        }
+    }
+    // 纯色或几乎没有细节的图片保持居中
+    (total > 0.0).then(|| (sum_x / total / width as f32, sum_y / total / height as f32))
+}
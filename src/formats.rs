@@ -0,0 +1,99 @@
+//! Which files count as images.
+//!
+//! The accepted extensions are derived from the codecs compiled into the
+//! `image` crate, adjusted by the user's include/exclude lists. Files whose
+//! extension is missing or unknown can optionally be identified by sniffing
+//! their first bytes.
+
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::Read,
+    path::Path,
+};
+
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+
+/// User overrides for the supported-format list (`formats` in the config).
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct FormatSettings {
+    /// Extra extensions to accept, e.g. `jfif`. Decoding goes by content.
+    #[serde(default)]
+    pub include_extensions: Vec<String>,
+    /// Extensions to ignore even though a codec is available, e.g. `ico`.
+    #[serde(default)]
+    pub exclude_extensions: Vec<String>,
+    /// Identify files with no or an unknown extension by their content.
+    #[serde(default)]
+    pub sniff_content: bool,
+}
+
+/// Whether this build can decode `format`.
+pub fn can_decode(format: ImageFormat) -> bool {
+    match format {
+        // image 0.24 只有在开启编码器 ("avif") 时才报告可读，解码实际需要 "avif-decoder"，
+        // 所以这里看本 crate 自己的 avif 特性
+        ImageFormat::Avif => cfg!(feature = "avif"),
+        format => format.reading_enabled(),
+    }
+}
+
+/// All extensions of formats this build can decode, lowercase.
+pub fn decodable_extensions() -> Vec<&'static str> {
+    ImageFormat::all().filter(|f| can_decode(*f)).flat_map(|f| f.extensions_str().iter().copied()).collect()
+}
+
+/// Compiled form of [`FormatSettings`] used while scanning.
+#[derive(Clone, Debug)]
+pub struct FormatFilter {
+    extensions: HashSet<String>,
+    excluded: HashSet<String>,
+    sniff_content: bool,
+}
+
+impl Default for FormatFilter {
+    fn default() -> Self { Self::new(&FormatSettings::default()) }
+}
+
+impl FormatFilter {
+    pub fn new(settings: &FormatSettings) -> Self {
+        let normalize = |ext: &String| ext.trim().trim_start_matches('.').to_lowercase();
+        let excluded: HashSet<String> = settings.exclude_extensions.iter().map(normalize).collect();
+        let extensions = decodable_extensions()
+            .into_iter()
+            .map(str::to_string)
+            .chain(settings.include_extensions.iter().map(normalize))
+            .filter(|ext| !ext.is_empty() && !excluded.contains(ext))
+            .collect();
+        Self { extensions, excluded, sniff_content: settings.sniff_content }
+    }
+
+    /// Accepted extensions, sorted, for display in the settings panel.
+    pub fn extensions(&self) -> Vec<&str> {
+        let mut extensions: Vec<&str> = self.extensions.iter().map(String::as_str).collect();
+        extensions.sort_unstable();
+        extensions
+    }
+
+    /// Whether `path` should be treated as an image. Only the extension is
+    /// checked unless content sniffing is enabled and the extension is
+    /// missing or not recognised (explicitly excluded extensions stay excluded).
+    pub fn matches(&self, path: &Path) -> bool {
+        let extension = path.extension().map(|s| s.to_string_lossy().to_lowercase());
+        match extension {
+            Some(ext) if self.extensions.contains(&ext) => true,
+            Some(ext) if self.excluded.contains(&ext) => false,
+            _ => self.sniff_content && self.sniff(path),
+        }
+    }
+
+    fn sniff(&self, path: &Path) -> bool {
+        let mut header = [0u8; 32];
+        let Ok(read) = File::open(path).and_then(|mut file| file.read(&mut header)) else { return false };
+        image::guess_format(&header[..read]).is_ok_and(|format| {
+            // 识别出的格式如果被用户排除了，也不接受
+            can_decode(format) && !format.extensions_str().iter().any(|ext| self.excluded.contains(*ext))
+        })
+    }
+}
@@ -0,0 +1,93 @@
+//! Photos shown so far, for back/forward navigation (`photo_widget_history.json`).
+//!
+//! Works like a browser history: going back and then showing a new photo
+//! drops the entries after the current one. The oldest entries are dropped
+//! once the configured length is exceeded.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::index::rebase;
+
+pub const HISTORY_FILE: &str = "photo_widget_history.json";
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct History {
+    entries: Vec<PathBuf>,
+    // 当前显示的条目；entries 为空时无意义
+    position: usize,
+}
+
+impl History {
+    /// Loads the saved history, dropping photos that no longer exist.
+    pub fn load(path: &Path) -> Self {
+        let mut history: Self = fs::read_to_string(path).ok().and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default();
+        let current = history.current().map(Path::to_path_buf);
+        history.entries.retain(|entry| entry.is_file());
+        history.position = current
+            .and_then(|current| history.entries.iter().position(|entry| *entry == current))
+            .unwrap_or(history.entries.len().saturating_sub(1));
+        history
+    }
+
+    pub fn save(&self, path: &Path) {
+        if let Ok(json) = serde_json::to_string(self) { let _ = fs::write(path, json); }
+    }
+
+    pub fn len(&self) -> usize { self.entries.len() }
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    pub fn current(&self) -> Option<&Path> { self.entries.get(self.position).map(PathBuf::as_path) }
+
+    /// Records a newly shown photo after the current one, keeping at most
+    /// `max_len` entries.
+    pub fn push(&mut self, path: PathBuf, max_len: usize) {
+        self.entries.truncate(self.position + 1);
+        self.entries.push(path);
+        let excess = self.entries.len().saturating_sub(max_len.max(1));
+        self.entries.drain(..excess);
+        self.position = self.entries.len() - 1;
+    }
+
+    /// Steps back one photo. `None` at the oldest entry.
+    pub fn back(&mut self) -> Option<PathBuf> {
+        if self.position == 0 || self.entries.is_empty() {
+            return None;
+        }
+        self.position -= 1;
+        self.entries.get(self.position).cloned()
+    }
+
+    /// Steps forward again after [`History::back`]. `None` at the newest
+    /// entry, where the caller picks a new photo instead.
+    pub fn forward(&mut self) -> Option<PathBuf> {
+        if self.position + 1 >= self.entries.len() {
+            return None;
+        }
+        self.position += 1;
+        self.entries.get(self.position).cloned()
+    }
+
+    /// Drops a deleted photo, or every photo below a removed directory.
+    pub fn remove(&mut self, path: &Path) {
+        let before = self.entries[..self.position.min(self.entries.len())].iter().filter(|entry| entry.starts_with(path)).count();
+        self.entries.retain(|entry| !entry.starts_with(path));
+        self.position = self.position.saturating_sub(before).min(self.entries.len().saturating_sub(1));
+    }
+
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        for entry in self.entries.iter_mut().filter(|entry| entry.starts_with(from)) {
+            *entry = rebase(entry, from, to);
+        }
+    }
+
+    pub fn set_max_len(&mut self, max_len: usize) {
+        let excess = self.entries.len().saturating_sub(max_len.max(1));
+        self.entries.drain(..excess);
+        self.position = self.position.saturating_sub(excess);
+    }
+}
@@ -0,0 +1,273 @@
+//! On-disk cache of per-image facts (`photo_widget_index.json`).
+//!
+//! Probing an image's dimensions means opening and parsing its header, which
+//! is slow on network shares with tens of thousands of photos. The index
+//! remembers what was learned about every file together with its mtime and
+//! size, so a rescan only re-probes files that actually changed.
+//!
+//! It also holds the user's manual rotations and focus points, so unlike
+//! the cached facts these are lost when the file is deleted or an entry is
+//! pruned.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use image::io::Reader as ImageReader;
+use serde::{Deserialize, Serialize};
+
+use crate::exif_data::{orientation_swaps_axes, CaptureDate, ExifData};
+
+pub const INDEX_FILE: &str = "photo_widget_index.json";
+
+/// Bumped whenever [`IndexEntry`] gains probed fields, so entries written by
+/// an older version are re-probed once (user data such as rotations is kept).
+pub const INDEX_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct IndexEntry {
+    /// Modification time in milliseconds since the Unix epoch.
+    pub mtime: u64,
+    /// File size in bytes.
+    pub size: u64,
+    /// Stored pixel size, before EXIF orientation is applied.
+    pub width: u32,
+    pub height: u32,
+    /// EXIF orientation tag (1-8), 0 when the file has none.
+    #[serde(default)]
+    pub exif_orientation: u16,
+    /// EXIF capture date, if the camera wrote one.
+    #[serde(default)]
+    pub capture_date: Option<CaptureDate>,
+    /// Manual correction in clockwise quarter turns, applied on top of the
+    /// EXIF orientation. Kept when the file is re-probed.
+    #[serde(default)]
+    pub user_rotation: u8,
+    /// Hashes for duplicate detection, computed only when it is enabled;
+    /// see [`crate::duplicates`].
+    #[serde(default)]
+    pub content_hash: Option<u64>,
+    #[serde(default)]
+    pub perceptual_hash: Option<u64>,
+    /// Whether the perceptual hash was computed, so images that cannot be
+    /// decoded are not tried on every scan.
+    #[serde(default)]
+    pub perceptual_hash_tried: bool,
+    /// Detected focus point for smart cropping, in 0..1 coordinates of the
+    /// displayed image; see [`crate::focus`].
+    #[serde(default)]
+    pub focus: Option<(f32, f32)>,
+    /// Whether focus detection ran, also when it found no focus point
+    /// (flat or tiny images).
+    #[serde(default)]
+    pub focus_detected: bool,
+    /// Focus point set by dragging the image, which wins over the detected
+    /// one. Kept when the file is re-probed.
+    #[serde(default)]
+    pub user_focus: Option<(f32, f32)>,
+    // 版本升级后需要重新探测的旧条目
+    #[serde(skip)]
+    stale: bool,
+}
+
+impl IndexEntry {
+    /// Reads the image header and EXIF block. `None` if the file is not a decodable image.
+    pub fn probe(path: &Path, stamp: FileStamp) -> Option<Self> {
+        // 按内容识别格式，扩展名错误的文件也能正确探测
+        let (width, height) = ImageReader::open(path).ok()?.with_guessed_format().ok()?.into_dimensions().ok()?;
+        let exif = ExifData::read(path).unwrap_or_default();
+        Some(Self {
+            mtime: stamp.mtime,
+            size: stamp.size,
+            width,
+            height,
+            exif_orientation: exif.orientation,
+            capture_date: exif.capture_date,
+            user_rotation: 0,
+            content_hash: None,
+            perceptual_hash: None,
+            perceptual_hash_tried: false,
+            focus: None,
+            focus_detected: false,
+            user_focus: None,
+            stale: false,
+        })
+    }
+
+    /// Pixel size as displayed, after EXIF orientation and manual rotation.
+    pub fn display_size(&self) -> (u32, u32) {
+        if orientation_swaps_axes(self.exif_orientation) != (self.user_rotation % 2 == 1) {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+
+    /// Where `Cover` centers its crop, if anywhere but the middle.
+    pub fn focus_point(&self) -> Option<(f32, f32)> { self.user_focus.or(self.focus) }
+}
+
+/// File facts that decide whether a cached [`IndexEntry`] is still valid.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FileStamp {
+    pub mtime: u64,
+    pub size: u64,
+}
+
+impl FileStamp {
+    pub fn read(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis() as u64);
+        Some(Self { mtime, size: metadata.len() })
+    }
+}
+
+/// Every indexed image by path. Besides the cache it holds user data
+/// (`user_rotation`, `user_focus`), so it is not safe to throw away.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ImageIndex {
+    #[serde(default)]
+    version: u32,
+    entries: HashMap<PathBuf, IndexEntry>,
+    /// Set whenever an entry is added, replaced or removed, so unchanged
+    /// indexes are not rewritten after every scan.
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl Default for ImageIndex {
+    fn default() -> Self {
+        Self { version: INDEX_VERSION, entries: HashMap::new(), dirty: false }
+    }
+}
+
+impl ImageIndex {
+    /// Loads the index, starting empty if the file is missing or unreadable.
+    pub fn load(path: &Path) -> Self {
+        let mut index: Self = fs::read_to_string(path).ok().and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default();
+        if index.version != INDEX_VERSION {
+            index.entries.values_mut().for_each(|entry| entry.stale = true);
+            index.version = INDEX_VERSION;
+        }
+        index
+    }
+
+    /// Writes the index if anything changed since it was loaded or last saved.
+    pub fn save(&mut self, path: &Path) {
+        if !self.dirty {
+            return;
+        }
+        if let Ok(json) = serde_json::to_string(self) && fs::write(path, json).is_ok() {
+            self.dirty = false;
+        }
+    }
+
+    pub fn len(&self) -> usize { self.entries.len() }
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+    pub fn is_dirty(&self) -> bool { self.dirty }
+
+    pub fn get(&self, path: &Path) -> Option<&IndexEntry> { self.entries.get(path) }
+
+    /// The cached entry for `path`, if it was indexed with the same mtime and size.
+    pub fn cached(&self, path: &Path, stamp: FileStamp) -> Option<&IndexEntry> {
+        self.entries.get(path).filter(|e| !e.stale && e.mtime == stamp.mtime && e.size == stamp.size)
+    }
+
+    /// Stores freshly probed facts for `path`. User data of an existing
+    /// entry (manual rotation and focus point) carries over.
+    pub fn insert(&mut self, path: PathBuf, mut entry: IndexEntry) {
+        if let Some(old) = self.entries.get(&path) {
+            entry.user_rotation = old.user_rotation;
+            entry.user_focus = old.user_focus;
+        }
+        self.entries.insert(path, entry);
+        self.dirty = true;
+    }
+
+    /// Sets the manual rotation (clockwise quarter turns) of an indexed image.
+    pub fn set_user_rotation(&mut self, path: &Path, quarter_turns: u8) {
+        if let Some(entry) = self.entries.get_mut(path) {
+            if entry.user_rotation != quarter_turns % 4 {
+                // 焦点坐标是按显示方向记录的，旋转后作废
+                entry.focus = None;
+                entry.focus_detected = false;
+                entry.user_focus = None;
+            }
+            entry.user_rotation = quarter_turns % 4;
+            self.dirty = true;
+        }
+    }
+
+    /// Stores the result of focus detection; `None` records that the image
+    /// has no focus point, so it is not analysed again.
+    pub fn set_detected_focus(&mut self, path: &Path, focus: Option<(f32, f32)>) {
+        if let Some(entry) = self.entries.get_mut(path) {
+            entry.focus = focus;
+            entry.focus_detected = true;
+            self.dirty = true;
+        }
+    }
+
+    /// Sets or (with `None`) clears the focus point the user chose.
+    pub fn set_user_focus(&mut self, path: &Path, focus: Option<(f32, f32)>) {
+        if let Some(entry) = self.entries.get_mut(path) {
+            entry.user_focus = focus;
+            self.dirty = true;
+        }
+    }
+
+    /// Stores the duplicate detection hashes of an indexed image; `None`
+    /// keeps the current value. `Some(None)` records a perceptual hash that
+    /// could not be computed.
+    pub fn set_hashes(&mut self, path: &Path, content: Option<u64>, perceptual: Option<Option<u64>>) {
+        if let Some(entry) = self.entries.get_mut(path) {
+            entry.content_hash = content.or(entry.content_hash);
+            if let Some(perceptual) = perceptual {
+                entry.perceptual_hash = perceptual.or(entry.perceptual_hash);
+                entry.perceptual_hash_tried = true;
+            }
+            self.dirty = true;
+        }
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        if self.entries.remove(path).is_some() {
+            self.dirty = true;
+        }
+    }
+
+    /// Moves the entry of a renamed file, or every entry below a renamed directory.
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        let moved: Vec<PathBuf> = self.entries.keys().filter(|path| path.starts_with(from)).cloned().collect();
+        for old in moved {
+            if let Some(entry) = self.entries.remove(&old) {
+                self.entries.insert(rebase(&old, from, to), entry);
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// Drops entries below any of `roots` that were not `seen` by the scan
+    /// that just walked them. Entries of other folders are kept, so removing
+    /// and re-adding a folder does not throw its cache away. Images that
+    /// folder rules now exclude are dropped with their user data.
+    pub fn prune(&mut self, roots: &[PathBuf], seen: &HashSet<PathBuf>) {
+        let before = self.entries.len();
+        self.entries.retain(|path, _| seen.contains(path) || !roots.iter().any(|root| path.starts_with(root)));
+        if self.entries.len() != before {
+            self.dirty = true;
+        }
+    }
+}
+
+/// `path` with its `from` prefix replaced by `to` (`path` itself if it is `from`).
+pub fn rebase(path: &Path, from: &Path, to: &Path) -> PathBuf {
+    match path.strip_prefix(from) {
+        Ok(rest) if rest.as_os_str().is_empty() => to.to_path_buf(),
+        Ok(rest) => to.join(rest),
+        Err(_) => path.to_path_buf(),
+    }
+}
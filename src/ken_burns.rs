@@ -0,0 +1,104 @@
+//! The Ken Burns effect: a slow pan and zoom across a still photo in
+//! `Cover` mode.
+//!
+//! A move goes from one crop of the image to another. Crops always have the
+//! window's aspect ratio and stay inside the image, so no empty border is
+//! ever shown.
+
+use std::time::Duration;
+
+use egui::{lerp, vec2, Rect, Vec2};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::layout;
+
+/// How long one pass takes when automatic refresh is off.
+pub const IDLE_PASS: Duration = Duration::from_secs(30);
+
+/// Where a move starts and ends.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum KenBurnsPath {
+    /// A random crop and zoom direction for every photo.
+    #[default]
+    Random,
+    /// From the whole crop into the center.
+    ZoomIn,
+    /// From the center out to the whole crop.
+    ZoomOut,
+    /// Across the image from the top left to the bottom right, zoomed in.
+    Pan,
+}
+
+/// Ken Burns settings (`ken_burns` in the config).
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct KenBurnsSettings {
+    pub enabled: bool,
+    pub path: KenBurnsPath,
+    /// Zoom at the closest point of a move; 1.0 only pans.
+    pub max_zoom: f32,
+    /// Passes per display interval. Faster moves go back and forth.
+    pub speed: f32,
+}
+
+impl Default for KenBurnsSettings {
+    fn default() -> Self {
+        Self { enabled: false, path: KenBurnsPath::Random, max_zoom: 1.25, speed: 1.0 }
+    }
+}
+
+impl KenBurnsSettings {
+    /// Position along the move, 0 to 1, `elapsed` after the photo appeared
+    /// when photos stay up for `display_time`.
+    pub fn progress(&self, elapsed: Duration, display_time: Duration) -> f32 {
+        let display_time = if display_time.is_zero() { IDLE_PASS } else { display_time };
+        let passes = elapsed.as_secs_f32() * self.speed.max(0.01) / display_time.as_secs_f32();
+        // 来回移动：奇数趟倒着走，每趟两端放慢，避免掉头时跳动
+        let t = if (passes as u32).is_multiple_of(2) { passes.fract() } else { 1.0 - passes.fract() };
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// A move for the next photo.
+    pub fn new_move(&self, rng: &mut impl Rng) -> KenBurnsMove {
+        let zoom = self.max_zoom.max(1.0);
+        let center = vec2(0.5, 0.5);
+        let (from, to) = match self.path {
+            KenBurnsPath::ZoomIn => (Crop { position: center, zoom: 1.0 }, Crop { position: center, zoom }),
+            KenBurnsPath::ZoomOut => (Crop { position: center, zoom }, Crop { position: center, zoom: 1.0 }),
+            KenBurnsPath::Pan => (Crop { position: vec2(0.0, 0.0), zoom }, Crop { position: vec2(1.0, 1.0), zoom }),
+            KenBurnsPath::Random => {
+                let mut random_crop = |zoom| Crop { position: vec2(rng.gen_range(0.0..=1.0), rng.gen_range(0.0..=1.0)), zoom };
+                let (near, far) = (random_crop(zoom), random_crop(1.0));
+                if rng.gen_bool(0.5) { (near, far) } else { (far, near) }
+            }
+        };
+        KenBurnsMove { from, to }
+    }
+}
+
+/// A crop with the window's aspect ratio. `position` places it within the
+/// room the image leaves on each axis (0 = left/top, 1 = right/bottom),
+/// `zoom` shrinks it relative to the centered `Cover` crop.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Crop {
+    pub position: Vec2,
+    pub zoom: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct KenBurnsMove {
+    pub from: Crop,
+    pub to: Crop,
+}
+
+impl KenBurnsMove {
+    /// Texture coordinates `progress` of the way along the move.
+    pub fn uv(&self, texture_size: Vec2, window_size: Vec2, progress: f32) -> Rect {
+        let t = progress.clamp(0.0, 1.0);
+        let zoom = lerp(self.from.zoom..=self.to.zoom, t).max(1.0);
+        let position = (self.from.position + (self.to.position - self.from.position) * t).clamp(Vec2::ZERO, vec2(1.0, 1.0));
+        let size = layout::cover_uv(texture_size, window_size).size() / zoom;
+        Rect::from_min_size(((vec2(1.0, 1.0) - size) * position).to_pos2(), size)
+    }
+}
@@ -0,0 +1,123 @@
+//! Window geometry: how big the widget should be, where it should move when
+//! it is resized, and which part of the texture is visible.
+
+use egui::{pos2, vec2, Pos2, Rect, Vec2};
+
+use crate::config::{AppConfig, FitMode, ResizeAnchor};
+
+pub const SETTINGS_WINDOW_SIZE: Vec2 = vec2(500.0, 600.0);
+
+/// The configured base size (landscape or portrait preset) for an image.
+pub fn preset_size(config: &AppConfig, image_size: Vec2) -> Vec2 {
+    if image_size.x >= image_size.y {
+        vec2(config.landscape_width, config.landscape_height)
+    } else {
+        vec2(config.portrait_width, config.portrait_height)
+    }
+}
+
+/// Window size for the current image. `Cover` and `Letterbox` use the preset
+/// as is, `Contain` grows one side of the preset so the window has the
+/// image's aspect ratio.
+pub fn window_size(config: &AppConfig, image_size: Option<Vec2>) -> Vec2 {
+    let Some(texture_size) = image_size else {
+        return vec2(config.landscape_width, config.landscape_height);
+    };
+    let image_aspect = texture_size.x / texture_size.y;
+    let preset = preset_size(config, texture_size);
+    let (target_width_preset, target_height_preset) = (preset.x, preset.y);
+
+    let target_aspect = if target_height_preset > 0.0 { target_width_preset / target_height_preset } else { image_aspect };
+
+    match config.fit_mode {
+        FitMode::Cover | FitMode::Letterbox => preset,
+        FitMode::Contain => {
+            let (new_width, new_height) = if image_aspect > target_aspect {
+                (target_height_preset * image_aspect, target_height_preset)
+            } else {
+                (target_width_preset, target_width_preset / image_aspect)
+            };
+            vec2(new_width, new_height)
+        }
+    }
+}
+
+/// Size in physical pixels the image is shown at: the window size for it
+/// times the display scale factor, and times the Ken Burns zoom, which
+/// shows a smaller part of the image. Decoding anything larger is wasted.
+pub fn display_pixels(config: &AppConfig, image_size: Vec2, pixels_per_point: f32) -> Vec2 {
+    let zoom = if config.fit_mode == FitMode::Cover && config.ken_burns.enabled { config.ken_burns.max_zoom.max(1.0) } else { 1.0 };
+    window_size(config, Some(image_size)) * pixels_per_point * zoom
+}
+
+/// New window position after a resize from `old_size` to `new_size`, so that
+/// the chosen anchor point stays where it was.
+pub fn anchored_position(anchor: ResizeAnchor, current_pos: Pos2, old_size: Vec2, new_size: Vec2) -> Pos2 {
+    let delta = new_size - old_size;
+    match anchor {
+        ResizeAnchor::Center => current_pos - delta / 2.0,
+        ResizeAnchor::TopLeft => current_pos,
+        ResizeAnchor::TopRight => current_pos - vec2(delta.x, 0.0),
+        ResizeAnchor::BottomLeft => current_pos - vec2(0.0, delta.y),
+        ResizeAnchor::BottomRight => current_pos - delta,
+    }
+}
+
+/// Pulls a window that is (partly) off-screen back inside the monitor.
+/// Returns `None` when the window is already fully visible.
+pub fn clamp_to_screen(window_pos: Pos2, window_size: Vec2, screen_size: Vec2) -> Option<Pos2> {
+    let mut new_pos = window_pos;
+    let mut changed = false;
+
+    if window_pos.x < 0.0 { new_pos.x = 0.0; changed = true; }
+    if window_pos.y < 0.0 { new_pos.y = 0.0; changed = true; }
+    if window_pos.x + window_size.x > screen_size.x { new_pos.x = screen_size.x - window_size.x; changed = true; }
+    if window_pos.y + window_size.y > screen_size.y { new_pos.y = screen_size.y - window_size.y; changed = true; }
+
+    changed.then_some(new_pos)
+}
+
+/// Texture coordinates of a centered crop with the aspect ratio of `available_size`.
+pub fn cover_uv(texture_size: Vec2, available_size: Vec2) -> Rect {
+    focused_cover_uv(texture_size, available_size, pos2(0.5, 0.5))
+}
+
+/// Like [`cover_uv`], but centered on `focus` (texture coordinates) as far
+/// as the crop can move without leaving the image.
+pub fn focused_cover_uv(texture_size: Vec2, available_size: Vec2, focus: Pos2) -> Rect {
+    let aspect_ratio = texture_size.x / texture_size.y;
+    let available_aspect_ratio = available_size.x / available_size.y;
+    let start = |size: f32, center: f32| (center - size / 2.0).min(1.0 - size).max(0.0);
+    if aspect_ratio > available_aspect_ratio {
+        let uv_width = available_aspect_ratio / aspect_ratio;
+        let uv_x = start(uv_width, focus.x);
+        Rect::from_min_max(pos2(uv_x, 0.0), pos2(uv_x + uv_width, 1.0))
+    } else {
+        let uv_height = aspect_ratio / available_aspect_ratio;
+        let uv_y = start(uv_height, focus.y);
+        Rect::from_min_max(pos2(0.0, uv_y), pos2(1.0, uv_y + uv_height))
+    }
+}
+
+/// Texture coordinates for the given fit mode.
+pub fn image_uv(fit_mode: FitMode, texture_size: Vec2, available_size: Vec2) -> Rect {
+    match fit_mode {
+        FitMode::Cover => cover_uv(texture_size, available_size),
+        FitMode::Contain | FitMode::Letterbox => Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)),
+    }
+}
+
+/// Where an image goes inside `rect` and which part of its texture is shown
+/// there. `Cover` fills the rect with a centered crop, `Contain` and
+/// `Letterbox` show the whole image centered in the rect, with bars when the
+/// aspect ratios differ.
+pub fn image_placement(fit_mode: FitMode, texture_size: Vec2, rect: Rect) -> (Rect, Rect) {
+    let uv = image_uv(fit_mode, texture_size, rect.size());
+    match fit_mode {
+        FitMode::Cover => (rect, uv),
+        FitMode::Contain | FitMode::Letterbox => {
+            let scale = (rect.width() / texture_size.x).min(rect.height() / texture_size.y);
+            (Rect::from_center_size(rect.center(), texture_size * scale), uv)
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Photo Widget: a borderless desktop widget that shows photos from local folders.
+//!
+//! Everything that does not need a window (configuration, folder scanning,
+//! playlist order, window geometry, decoding) lives in headless modules so it
+//! can be tested on machines without a display. [`app`] ties them together
+//! into the eframe application and the binary only wires up the tray icon.
+
+pub mod animation;
+pub mod app;
+pub mod cache;
+pub mod caption;
+pub mod config;
+pub mod decoder;
+pub mod duplicates;
+pub mod exif_data;
+pub mod focus;
+pub mod formats;
+pub mod history;
+pub mod index;
+pub mod ken_burns;
+pub mod layout;
+pub mod library;
+pub mod memories;
+pub mod platform;
+pub mod playlist;
+pub mod ratings;
+pub mod render;
+pub mod scanner;
+pub mod transition;
+pub mod watcher;
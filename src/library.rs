@@ -0,0 +1,391 @@
+//! Discovering images inside the configured folders.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::{
+    config::{AppConfig, FolderEntry, ImageOrientationFilter},
+    duplicates::{self, DuplicateSettings},
+    focus,
+    formats::FormatFilter,
+    index::{FileStamp, ImageIndex, IndexEntry},
+};
+
+/// Everything that decides whether a file ends up in the playlist.
+#[derive(Clone, Debug)]
+pub struct ScanFilter {
+    pub orientation: ImageOrientationFilter,
+    pub limits: ImageLimits,
+    pub formats: FormatFilter,
+    /// Images the user never wants to see again.
+    pub hidden: Arc<HashSet<PathBuf>>,
+    /// Patterns and limits of the configured folders. Files outside all of
+    /// them are only filtered by format.
+    pub folders: Arc<Vec<FolderRules>>,
+    /// Which hashes accepted images need for duplicate detection.
+    pub duplicates: DuplicateSettings,
+    /// Detect a focus point for accepted images that have none yet.
+    pub smart_crop: bool,
+}
+
+impl Default for ScanFilter {
+    fn default() -> Self {
+        Self {
+            orientation: ImageOrientationFilter::Both,
+            limits: ImageLimits::default(),
+            formats: FormatFilter::default(),
+            hidden: Arc::default(),
+            folders: Arc::default(),
+            duplicates: DuplicateSettings::default(),
+            smart_crop: false,
+        }
+    }
+}
+
+impl ScanFilter {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            orientation: config.orientation_filter,
+            limits: config.image_limits,
+            formats: FormatFilter::new(&config.formats),
+            hidden: Arc::default(),
+            folders: Arc::new(config.folders.iter().filter(|folder| folder.enabled).map(FolderRules::new).collect()),
+            duplicates: config.duplicates,
+            smart_crop: config.smart_crop,
+        }
+    }
+
+    pub fn is_hidden(&self, path: &Path) -> bool { self.hidden.contains(path) }
+
+    /// The rules of the innermost configured folder containing `path`.
+    fn rules_for(&self, path: &Path) -> Option<&FolderRules> {
+        self.folders.iter().filter(|rules| path.starts_with(&rules.root)).max_by_key(|rules| rules.root.components().count())
+    }
+
+    /// Whether the folder rules allow `path`, checking every directory
+    /// between the configured folder and the file (used for single files
+    /// reported by the watcher; walks prune whole directories instead).
+    pub fn in_scope(&self, path: &Path) -> bool {
+        self.rules_for(path).is_none_or(|rules| rules.allows(path))
+    }
+
+    /// The entries below `folder` (which may be a subfolder of a configured
+    /// folder) that its folder rules allow.
+    pub fn walk<'a>(&'a self, folder: &Path) -> Box<dyn Iterator<Item = walkdir::Result<DirEntry>> + 'a> {
+        let Some(rules) = self.rules_for(folder) else { return Box::new(WalkDir::new(folder).into_iter()) };
+        let depth = rules.depth_of(folder);
+        if depth > 0 && !rules.allows_dir(folder) {
+            return Box::new(std::iter::empty());
+        }
+        let mut walker = WalkDir::new(folder).follow_links(rules.follow_symlinks);
+        if let Some(max_depth) = rules.max_depth {
+            // 文件比它所在的目录深一层
+            walker = walker.max_depth((max_depth + 1).saturating_sub(depth));
+        }
+        Box::new(walker.into_iter().filter_entry(move |entry| {
+            entry.depth() == 0 || if entry.file_type().is_dir() { rules.allows_entry(entry.path()) } else { rules.allows_file(entry.path()) }
+        }))
+    }
+
+    /// Whether `path` looks like an image worth probing (cheap, no decoding).
+    /// Symlinked files only count in folders that follow symlinks.
+    pub fn is_candidate(&self, path: &Path) -> bool {
+        let is_file = if self.rules_for(path).is_none_or(|rules| rules.follow_symlinks) {
+            path.is_file()
+        } else {
+            // is_file 会跟随链接，这里要看链接本身
+            fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_file())
+        };
+        is_file && self.formats.matches(path)
+    }
+
+    /// Whether a probed image passes the filters.
+    pub fn accepts(&self, info: &IndexEntry) -> bool {
+        let (width, height) = info.display_size();
+        self.orientation.accepts(width, height, self.limits.square_tolerance) && self.limits.accepts(width, height, info.size)
+    }
+}
+
+/// Glob patterns of one list; see [`FolderEntry::include`].
+#[derive(Clone, Debug)]
+struct PatternSet {
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl PatternSet {
+    /// Invalid patterns are skipped; the settings panel points them out.
+    fn new(patterns: &[String]) -> Self {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            let Ok(glob) = compile_pattern(pattern) else { continue };
+            if pattern.contains('/') { paths.add(glob); } else { names.add(glob); }
+        }
+        Self { names: names.build().unwrap_or_else(|_| GlobSet::empty()), paths: paths.build().unwrap_or_else(|_| GlobSet::empty()) }
+    }
+
+    fn is_empty(&self) -> bool { self.names.is_empty() && self.paths.is_empty() }
+
+    fn matches(&self, relative: &Path) -> bool {
+        relative.file_name().is_some_and(|name| self.names.is_match(name)) || self.paths.is_match(relative)
+    }
+}
+
+fn compile_pattern(pattern: &str) -> Result<globset::Glob, globset::Error> {
+    GlobBuilder::new(pattern.trim_start_matches('/')).literal_separator(true).case_insensitive(true).build()
+}
+
+/// Why `pattern` is not a valid glob, for the settings panel.
+pub fn pattern_error(pattern: &str) -> Option<String> {
+    compile_pattern(pattern.trim()).err().map(|error| error.kind().to_string())
+}
+
+/// Which files below one configured folder are scanned, compiled from its
+/// [`FolderEntry`].
+#[derive(Clone, Debug)]
+pub struct FolderRules {
+    pub root: PathBuf,
+    include: PatternSet,
+    exclude: PatternSet,
+    max_depth: Option<usize>,
+    include_hidden: bool,
+    follow_symlinks: bool,
+}
+
+impl FolderRules {
+    pub fn new(folder: &FolderEntry) -> Self {
+        Self {
+            root: folder.path.clone(),
+            include: PatternSet::new(&folder.include),
+            exclude: PatternSet::new(&folder.exclude),
+            max_depth: folder.max_depth,
+            include_hidden: folder.include_hidden,
+            follow_symlinks: folder.follow_symlinks,
+        }
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path { path.strip_prefix(&self.root).unwrap_or(path) }
+
+    fn depth_of(&self, path: &Path) -> usize { self.relative(path).components().count() }
+
+    /// Checks the last component of `path` only; its parents were checked
+    /// when the walk entered them.
+    fn allows_entry(&self, path: &Path) -> bool {
+        (self.include_hidden || !is_hidden_file(path)) && !self.exclude.matches(self.relative(path))
+    }
+
+    fn allows_file(&self, path: &Path) -> bool {
+        self.allows_entry(path) && (self.include.is_empty() || self.include.matches(self.relative(path)))
+    }
+
+    /// Whether `dir` and every directory above it up to the root may be entered.
+    fn allows_dir(&self, dir: &Path) -> bool {
+        dir.ancestors().take_while(|ancestor| *ancestor != self.root).all(|ancestor| self.allows_entry(ancestor))
+    }
+
+    pub fn allows(&self, path: &Path) -> bool {
+        let depth = self.depth_of(path);
+        self.max_depth.is_none_or(|max_depth| depth <= max_depth + 1)
+            && self.allows_file(path)
+            && path.parent().is_none_or(|parent| self.allows_dir(parent))
+    }
+}
+
+/// Dot files, and on Windows also files with the hidden attribute.
+fn is_hidden_file(path: &Path) -> bool {
+    if path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.')) {
+        return true;
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0) {
+            return true;
+        }
+    }
+    false
+}
+
+impl ImageOrientationFilter {
+    /// Whether an image of the given pixel size passes this filter. Images
+    /// whose sides differ by at most `square_tolerance` (0.05 = 5 %) are
+    /// square and count as neither landscape nor portrait.
+    pub fn accepts(self, width: u32, height: u32, square_tolerance: f32) -> bool {
+        let is_square = is_square(width, height, square_tolerance);
+        match self {
+            ImageOrientationFilter::Both => true,
+            ImageOrientationFilter::Landscape => width > height && !is_square,
+            ImageOrientationFilter::Portrait => height > width && !is_square,
+            ImageOrientationFilter::Square => is_square,
+        }
+    }
+}
+
+fn is_square(width: u32, height: u32, tolerance: f32) -> bool {
+    let (long, short) = (width.max(height) as f32, width.min(height) as f32);
+    short > 0.0 && long / short - 1.0 <= tolerance.max(0.0)
+}
+
+/// Size and shape limits checked while indexing (`image_limits` in the
+/// config). Dimensions are as displayed, after EXIF orientation.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct ImageLimits {
+    /// How far from 1:1 an image may be and still count as square.
+    pub square_tolerance: f32,
+    /// Width divided by height.
+    pub min_aspect_ratio: Option<f32>,
+    pub max_aspect_ratio: Option<f32>,
+    /// Skips icons, thumbnails and other small images; 0 for no limit.
+    pub min_width: u32,
+    pub min_height: u32,
+    pub min_file_size_kb: Option<u64>,
+    pub max_file_size_mb: Option<u64>,
+}
+
+impl Default for ImageLimits {
+    fn default() -> Self {
+        Self { square_tolerance: 0.02, min_aspect_ratio: None, max_aspect_ratio: None, min_width: 0, min_height: 0, min_file_size_kb: None, max_file_size_mb: None }
+    }
+}
+
+impl ImageLimits {
+    pub fn accepts(&self, width: u32, height: u32, file_size: u64) -> bool {
+        let aspect_ratio = width as f32 / height.max(1) as f32;
+        width >= self.min_width
+            && height >= self.min_height
+            && self.min_aspect_ratio.is_none_or(|min| aspect_ratio >= min)
+            && self.max_aspect_ratio.is_none_or(|max| aspect_ratio <= max)
+            && self.min_file_size_kb.is_none_or(|min| file_size >= min.saturating_mul(1024))
+            && self.max_file_size_mb.is_none_or(|max| file_size <= max.saturating_mul(1024 * 1024))
+    }
+}
+
+/// Counters of a (possibly still running) scan, shared with the UI thread.
+#[derive(Default, Debug)]
+pub struct ScanStats {
+    /// Files with an image extension that were looked at.
+    pub files_seen: AtomicUsize,
+    /// Images that passed the filters and were handed out.
+    pub images_accepted: AtomicUsize,
+    /// Unreadable directories and files that could not be probed.
+    pub errors: AtomicUsize,
+}
+
+/// Looks `path` up in the shared index, probing it only if the cached entry
+/// is missing or stale. The lock is not held while the file is probed, so a
+/// slow network share does not block other users of the index.
+pub fn probe_shared(index: &Mutex<ImageIndex>, path: &Path) -> Option<IndexEntry> {
+    let Some(stamp) = FileStamp::read(path) else {
+        index.lock().unwrap().remove(path);
+        return None;
+    };
+    if let Some(entry) = index.lock().unwrap().cached(path, stamp) {
+        return Some(entry.clone());
+    }
+    let probed = IndexEntry::probe(path, stamp);
+    let mut index = index.lock().unwrap();
+    match probed {
+        Some(entry) => {
+            index.insert(path.to_path_buf(), entry);
+            // 返回合并了用户数据 (手动旋转等) 之后的条目
+            index.get(path).cloned()
+        }
+        None => { index.remove(path); None }
+    }
+}
+
+/// Computes the duplicate detection hashes `settings` asks for and `entry`
+/// lacks, and stores them in the shared index. Like [`probe_shared`] the
+/// file is read without holding the lock.
+pub fn hash_shared(index: &Mutex<ImageIndex>, path: &Path, entry: &IndexEntry, settings: &DuplicateSettings) {
+    if !settings.enabled {
+        return;
+    }
+    let content = if entry.content_hash.is_none() { duplicates::content_hash(path) } else { None };
+    let perceptual = (settings.near_duplicates && entry.perceptual_hash.is_none() && !entry.perceptual_hash_tried).then(|| duplicates::perceptual_hash(path));
+    if content.is_some() || perceptual.is_some() {
+        index.lock().unwrap().set_hashes(path, content, perceptual);
+    }
+}
+
+/// Detects the focus point of an accepted image that was not analysed yet,
+/// outside the index lock like [`hash_shared`].
+pub fn focus_shared(index: &Mutex<ImageIndex>, path: &Path, entry: &IndexEntry, smart_crop: bool) {
+    if !smart_crop || entry.focus.is_some() || entry.focus_detected {
+        return;
+    }
+    let point = focus::detect(path, entry.user_rotation);
+    index.lock().unwrap().set_detected_focus(path, point);
+}
+
+/// Walks every folder (as deep as its folder rules allow) and calls
+/// `on_found` for each image that passes `filter`, in directory order.
+/// Dimensions come from `index`, which is refreshed for new or modified files and, if the walk was
+/// not cancelled, pruned of files that are gone. Returns `false` when the walk
+/// stopped early because `cancel` was set.
+pub fn scan_folders(
+    folders: &[PathBuf],
+    filter: &ScanFilter,
+    index: &Mutex<ImageIndex>,
+    stats: &ScanStats,
+    cancel: &AtomicBool,
+    mut on_found: impl FnMut(PathBuf),
+) -> bool {
+    let mut seen = HashSet::new();
+    for folder in folders {
+        for entry in filter.walk(folder) {
+            if cancel.load(Ordering::Relaxed) {
+                return false;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(_) => { stats.errors.fetch_add(1, Ordering::Relaxed); continue; }
+            };
+            let path = entry.path();
+            if !filter.is_candidate(path) {
+                continue;
+            }
+            stats.files_seen.fetch_add(1, Ordering::Relaxed);
+            seen.insert(path.to_path_buf());
+            // 隐藏的图片保留索引条目 (以及其中的旋转设置)，只是不再显示
+            if filter.is_hidden(path) {
+                continue;
+            }
+            match probe_shared(index, path) {
+                Some(info) if filter.accepts(&info) => {
+                    hash_shared(index, path, &info, &filter.duplicates);
+                    focus_shared(index, path, &info, filter.smart_crop);
+                    stats.images_accepted.fetch_add(1, Ordering::Relaxed);
+                    on_found(path.to_path_buf());
+                }
+                Some(_) => {}
+                None => { stats.errors.fetch_add(1, Ordering::Relaxed); }
+            }
+        }
+    }
+    index.lock().unwrap().prune(folders, &seen);
+    true
+}
+
+/// Synchronous version of [`scan_folders`] that collects the accepted images.
+pub fn scan_image_files(folders: &[PathBuf], filter: &ScanFilter, index: &mut ImageIndex) -> Vec<PathBuf> {
+    let shared = Mutex::new(std::mem::take(index));
+    let mut image_files = Vec::new();
+    scan_folders(folders, filter, &shared, &ScanStats::default(), &AtomicBool::new(false), |path| image_files.push(path));
+    *index = shared.into_inner().unwrap();
+    image_files
+}
@@ -0,0 +1,128 @@
+//! "On this day" and date-range selections, for showing the widget like a
+//! memories frame.
+//!
+//! The date of an image is its EXIF capture date, or the local day its file
+//! was last modified when the camera wrote none. The playlist shows only the
+//! matching images and falls back to all of them when nothing matches.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{index::IndexEntry, platform};
+
+// 闰年各月之前的天数，用于比较不同年份的月日
+const DAYS_BEFORE_MONTH: [u16; 12] = [0, 31, 60, 91, 121, 152, 182, 213, 244, 274, 305, 335];
+
+/// A calendar day. Field order makes the derived ordering chronological.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Day {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Day {
+    pub fn new(year: i32, month: u8, day: u8) -> Self {
+        Self { year, month: month.clamp(1, 12), day: day.clamp(1, 31) }
+    }
+
+    /// The day a Unix timestamp falls on, `utc_offset` seconds east of UTC.
+    pub fn from_unix_seconds(seconds: i64, utc_offset: i64) -> Self {
+        // Howard Hinnant 的 civil_from_days 算法
+        let days = (seconds + utc_offset).div_euclid(86_400) + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u8;
+        let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 } as u8;
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Self { year: year as i32, month, day }
+    }
+
+    pub fn today() -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        Self::from_unix_seconds(now, platform::utc_offset_seconds())
+    }
+
+    /// The capture date of an indexed image, or the day it was last modified.
+    pub fn of_entry(entry: &IndexEntry, utc_offset: i64) -> Self {
+        match entry.capture_date {
+            Some(date) => Self::new(i32::from(date.year), date.month, date.day),
+            None => Self::from_unix_seconds((entry.mtime / 1000) as i64, utc_offset),
+        }
+    }
+
+    /// Position within a leap year (0-365), so February 29 has its own slot.
+    fn day_of_year(self) -> u16 {
+        DAYS_BEFORE_MONTH[usize::from(self.month.clamp(1, 12)) - 1] + u16::from(self.day) - 1
+    }
+
+    /// Days between the month and day of both dates, ignoring the year and
+    /// wrapping around New Year.
+    fn days_apart_in_year(self, other: Self) -> u16 {
+        let distance = self.day_of_year().abs_diff(other.day_of_year());
+        distance.min(366 - distance)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DateSelection {
+    /// Every image.
+    #[default]
+    All,
+    /// Images taken on today's month and day in earlier years.
+    OnThisDay,
+    /// Images taken between `from` and `to`, both included.
+    Range,
+}
+
+/// Which images the memories mode shows (`memories` in the config).
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct MemorySettings {
+    pub selection: DateSelection,
+    /// How many days before and after today still count as "on this day".
+    pub days_around: u16,
+    pub from: Day,
+    pub to: Day,
+}
+
+impl Default for MemorySettings {
+    fn default() -> Self {
+        Self { selection: DateSelection::All, days_around: 0, from: Day::new(2000, 1, 1), to: Day::new(2099, 12, 31) }
+    }
+}
+
+/// [`MemorySettings`] evaluated for one day. "On this day" changes at
+/// midnight, so the app builds a new filter when the date changes.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DateFilter {
+    settings: MemorySettings,
+    today: Day,
+    utc_offset: i64,
+}
+
+impl DateFilter {
+    /// `None` when every image is shown anyway.
+    pub fn new(settings: MemorySettings) -> Option<Self> {
+        Self::at(settings, Day::today(), platform::utc_offset_seconds())
+    }
+
+    pub fn at(settings: MemorySettings, today: Day, utc_offset: i64) -> Option<Self> {
+        (settings.selection != DateSelection::All).then_some(Self { settings, today, utc_offset })
+    }
+
+    pub fn today(&self) -> Day { self.today }
+
+    pub fn matches(&self, entry: &IndexEntry) -> bool {
+        let day = Day::of_entry(entry, self.utc_offset);
+        match self.settings.selection {
+            DateSelection::All => true,
+            DateSelection::OnThisDay => day.year < self.today.year && day.days_apart_in_year(self.today) <= self.settings.days_around,
+            DateSelection::Range => (self.settings.from..=self.settings.to).contains(&day),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Platform-specific window tweaks and system queries.
+//!
+//! The widget should behave like a desktop accessory rather than a regular
+//! application window: it must not show up in the taskbar and window managers
+//! should treat it as a helper window. Every platform exposes this differently,
+//! so all `cfg`-gated calls live here and `main()` only calls [`configure_window`].
+
+use winit::window::WindowBuilder;
+
+/// Application ID / WM_CLASS used on Linux, so users can target the widget with
+/// window manager or compositor rules (e.g. "keep below", "no shadow").
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+))]
+pub const APP_ID: &str = "photo-widget";
+
+/// Windows: hide the widget from the taskbar.
+#[cfg(target_os = "windows")]
+pub fn configure_window(wb: WindowBuilder) -> WindowBuilder {
+    use winit::platform::windows::WindowBuilderExtWindows;
+    wb.with_skip_taskbar(true)
+}
+
+/// X11 / Wayland: there is no portable "skip taskbar" flag in the window
+/// builder, so we mark the window as `_NET_WM_WINDOW_TYPE_UTILITY` on X11
+/// (which EWMH-compliant window managers keep out of the taskbar and pager)
+/// and set a stable application ID for both backends.
+#[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+))]
+pub fn configure_window(wb: WindowBuilder) -> WindowBuilder {
+    use winit::platform::x11::{WindowBuilderExtX11, XWindowType};
+
+    // X11 和 Wayland 共用同一个 name 字段：X11 下是 WM_CLASS，Wayland 下是 app_id
+    wb.with_name(APP_ID, "Photo Widget")
+        .with_x11_window_type(vec![XWindowType::Utility])
+}
+
+/// Other platforms (macOS, ...): nothing to adjust.
+#[cfg(not(any(
+    target_os = "windows",
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+)))]
+pub fn configure_window(wb: WindowBuilder) -> WindowBuilder {
+    wb
+}
+
+/// Offset of local time from UTC in seconds, for turning file times into
+/// local calendar days.
+#[cfg(target_os = "windows")]
+pub fn utc_offset_seconds() -> i64 {
+    use winapi::um::{timezoneapi::{GetTimeZoneInformation, TIME_ZONE_INFORMATION}, winnt::TIME_ZONE_ID_DAYLIGHT};
+    // SAFETY: 结构体全为整数和数组，零值合法；函数只写入传入的结构体
+    let mut info: TIME_ZONE_INFORMATION = unsafe { std::mem::zeroed() };
+    let zone = unsafe { GetTimeZoneInformation(&mut info) };
+    // UTC = 本地时间 + Bias (分钟)
+    let bias = info.Bias + if zone == TIME_ZONE_ID_DAYLIGHT { info.DaylightBias } else { info.StandardBias };
+    -i64::from(bias) * 60
+}
+
+#[cfg(unix)]
+pub fn utc_offset_seconds() -> i64 {
+    // SAFETY: localtime_r 是线程安全的版本，只写入传入的 tm
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&now, &mut tm).is_null() { 0 } else { tm.tm_gmtoff as i64 }
+    }
+}
+
+#[cfg(not(any(target_os = "windows", unix)))]
+pub fn utc_offset_seconds() -> i64 { 0 }
@@ -0,0 +1,382 @@
+//! Order in which images are shown.
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    ops::Bound,
+    path::{Path, PathBuf},
+};
+
+use rand::{distributions::WeightedIndex, prelude::Distribution, seq::SliceRandom, thread_rng, Rng};
+
+use crate::{
+    config::PlaybackOrder,
+    exif_data::CaptureDate,
+    index::{rebase, IndexEntry},
+    memories::DateFilter,
+};
+
+/// Where an image goes in the sequential orders. The path breaks ties, so
+/// every image has a distinct key.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct SortKey {
+    rank: u64,
+    name: String,
+    path: PathBuf,
+}
+
+impl SortKey {
+    /// `entry` supplies the modification time and capture date; without it
+    /// those orders put the image first (by mtime) or last (by capture date).
+    pub fn new(order: PlaybackOrder, path: &Path, entry: Option<&IndexEntry>) -> Self {
+        let rank = match order {
+            PlaybackOrder::ByModified => entry.map_or(0, |e| e.mtime),
+            // 没有拍摄日期的照片排在最后
+            PlaybackOrder::ByCaptureDate => entry.and_then(|e| e.capture_date).map_or(u64::MAX, CaptureDate::packed),
+            _ => 0,
+        };
+        let lowercase = |part: Option<&std::ffi::OsStr>| part.map_or_else(String::new, |s| s.to_string_lossy().to_lowercase());
+        let name = match order {
+            PlaybackOrder::ByName => lowercase(path.file_name()),
+            // 先按目录再按文件名；\0 保证同一目录的文件排在一起
+            PlaybackOrder::ByFolder => format!("{}\0{}", lowercase(path.parent().map(Path::as_os_str)), lowercase(path.file_name())),
+            _ => String::new(),
+        };
+        Self { rank, name, path: path.to_path_buf() }
+    }
+
+    pub fn path(&self) -> &Path { &self.path }
+}
+
+/// The images below one configured folder. Random orders first pick a group
+/// by weight and then an image inside it, so a small folder is not drowned
+/// out by a large one.
+#[derive(Debug, Clone)]
+struct FolderGroup {
+    root: PathBuf,
+    weight: f64,
+    // 随机播放时本组的顺序：index 之前的是本轮已经显示过的
+    files: Vec<PathBuf>,
+    index: usize,
+}
+
+impl FolderGroup {
+    fn new(root: PathBuf, weight: f64) -> Self {
+        Self { root, weight, files: Vec::new(), index: 0 }
+    }
+
+    /// Adds an image at a random position among the ones not shown yet in
+    /// this round ("inside-out" Fisher-Yates, so streaming images in one by
+    /// one yields the same distribution as shuffling them all at once).
+    fn insert(&mut self, path: PathBuf, shuffle: bool) {
+        self.files.push(path);
+        if shuffle {
+            let last = self.files.len() - 1;
+            let j = thread_rng().gen_range(self.index.min(last)..=last);
+            self.files.swap(j, last);
+        }
+    }
+
+    fn remove(&mut self, path: &Path) {
+        let before_index = self.files[..self.index.min(self.files.len())].iter().filter(|f| f.starts_with(path)).count();
+        self.files.retain(|f| !f.starts_with(path));
+        self.index -= before_index;
+    }
+
+    fn can_draw(&self, order: PlaybackOrder, new_round: bool) -> bool {
+        !self.files.is_empty() && (order == PlaybackOrder::Random || new_round || self.index < self.files.len())
+    }
+
+    /// The next image of this folder. Images with a higher weight are more
+    /// likely to be picked (in shuffle mode: earlier in the round).
+    fn draw(&mut self, order: PlaybackOrder, weights: &HashMap<PathBuf, f64>) -> Option<PathBuf> {
+        let weight = |path: &PathBuf| weights.get(path).copied().unwrap_or(1.0);
+        let mut rng = thread_rng();
+        if order == PlaybackOrder::Random {
+            if weights.is_empty() { return self.files.choose(&mut rng).cloned(); }
+            return self.files.choose_weighted(&mut rng, weight).ok().cloned();
+        }
+        if self.index >= self.files.len() {
+            self.files.shuffle(&mut rng);
+            self.index = 0;
+        }
+        if !weights.is_empty() && let Ok(distribution) = WeightedIndex::new(self.files[self.index..].iter().map(weight)) {
+            let pick = self.index + distribution.sample(&mut rng);
+            self.files.swap(self.index, pick);
+        }
+        let path = self.files.get(self.index).cloned();
+        self.index += 1;
+        path
+    }
+}
+
+/// The images of the library in the configured [`PlaybackOrder`]. The
+/// default, shuffle-without-repeat, shows every image of a folder once
+/// before that folder is reshuffled. With a [`DateFilter`] the matching
+/// images form a playlist of their own that is used while it is not empty.
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    order: PlaybackOrder,
+    // 第一组是兜底组 (根为空路径，匹配所有图片)，其后是配置的文件夹
+    groups: Vec<FolderGroup>,
+    members: HashSet<PathBuf>,
+    // 随机顺序下与默认值 1 不同的图片权重 (评分)
+    image_weights: HashMap<PathBuf, f64>,
+    // 随机顺序下已经抽好的后续图片，供预取
+    ahead: VecDeque<PathBuf>,
+    // 顺序模式：按排序键排好的图片
+    sorted: BTreeSet<SortKey>,
+    keys: HashMap<PathBuf, SortKey>,
+    // 顺序模式下最后显示的位置，下一张是排在它之后的第一张
+    last_key: Option<SortKey>,
+    date_filter: Option<DateFilter>,
+    // 符合日期条件的图片；非空时只从这里取下一张
+    focus: Option<Box<Playlist>>,
+}
+
+impl Default for Playlist {
+    fn default() -> Self {
+        Self {
+            order: PlaybackOrder::default(),
+            groups: vec![FolderGroup::new(PathBuf::new(), 1.0)],
+            members: HashSet::new(),
+            image_weights: HashMap::new(),
+            ahead: VecDeque::new(),
+            sorted: BTreeSet::new(),
+            keys: HashMap::new(),
+            last_key: None,
+            date_filter: None,
+            focus: None,
+        }
+    }
+}
+
+impl Playlist {
+    pub fn new(files: Vec<PathBuf>) -> Self {
+        let mut playlist = Self::default();
+        playlist.set_files(files);
+        playlist
+    }
+
+    pub fn order(&self) -> PlaybackOrder { self.order }
+
+    /// Switches the order and empties the playlist (keys depend on the order,
+    /// so the caller refills it). Starts from the beginning in sequential orders.
+    pub fn set_order(&mut self, order: PlaybackOrder) {
+        self.order = order;
+        self.last_key = None;
+        self.focus = None;
+        self.set_files(Vec::new());
+    }
+
+    /// Sets the configured folders and their weights and empties the
+    /// playlist. Images outside all of them share a group of weight 1.
+    pub fn set_folders(&mut self, folders: &[(PathBuf, f32)]) {
+        self.groups.truncate(1);
+        self.groups.extend(folders.iter().map(|(root, weight)| FolderGroup::new(root.clone(), f64::from(weight.max(0.0)))));
+        self.set_files(Vec::new());
+    }
+
+    /// Shows only the images whose date matches `filter` while there are
+    /// any, and all images otherwise. Empties the playlist; images need an
+    /// index entry to match, see [`Playlist::insert_with_entry`].
+    pub fn set_date_filter(&mut self, filter: Option<DateFilter>) {
+        self.date_filter = filter;
+        self.set_files(Vec::new());
+    }
+
+    pub fn date_filter(&self) -> Option<&DateFilter> { self.date_filter.as_ref() }
+
+    /// How many images match the date filter (0 without one).
+    pub fn matching_len(&self) -> usize { self.focus.as_ref().map_or(0, |focus| focus.len()) }
+
+    /// Replaces the contents and starts a new round. In sequential orders
+    /// the position is kept, so a rescan continues where it left off; images
+    /// added this way are sorted by path only, see [`Playlist::insert_with_entry`].
+    pub fn set_files(&mut self, files: Vec<PathBuf>) {
+        self.focus = self.date_filter.map(|_| {
+            let groups = self.groups.iter().map(|group| FolderGroup::new(group.root.clone(), group.weight)).collect();
+            let last_key = self.focus.as_ref().and_then(|focus| focus.last_key.clone());
+            Box::new(Self { order: self.order, groups, last_key, ..Self::default() })
+        });
+        for group in &mut self.groups {
+            group.files.clear();
+            group.index = 0;
+        }
+        self.members.clear();
+        self.image_weights.clear();
+        self.ahead.clear();
+        self.sorted.clear();
+        self.keys.clear();
+        for file in files { self.insert(file); }
+        if self.order == PlaybackOrder::Shuffle {
+            for group in &mut self.groups { group.files.shuffle(&mut thread_rng()); }
+        }
+    }
+
+    /// Makes an image more (or less) likely to be picked in random orders,
+    /// relative to the default weight of 1. Draws made in advance for
+    /// prefetching are kept; [`Playlist::set_files`] resets all weights.
+    pub fn set_image_weight(&mut self, path: PathBuf, weight: f64) {
+        if let Some(focus) = &mut self.focus { focus.set_image_weight(path.clone(), weight); }
+        if weight == 1.0 { self.image_weights.remove(&path); } else { self.image_weights.insert(path, weight.max(0.0)); }
+    }
+
+    /// Continues a sequential order after `key` (the photo shown last time).
+    pub fn resume_after(&mut self, key: SortKey) {
+        if let Some(focus) = &mut self.focus { focus.resume_after(key.clone()); }
+        if self.order.is_sequential() { self.last_key = Some(key); }
+    }
+
+    pub fn insert(&mut self, path: PathBuf) -> bool { self.insert_with_entry(path, None) }
+
+    /// Adds an image. In shuffle mode it lands at a random position among
+    /// the ones of its folder not shown yet in this round; sequential orders
+    /// sort it by its [`SortKey`]. Images already in the playlist are ignored.
+    pub fn insert_with_entry(&mut self, path: PathBuf, entry: Option<&IndexEntry>) -> bool {
+        if !self.members.insert(path.clone()) {
+            return false;
+        }
+        if let (Some(focus), Some(filter), Some(entry)) = (&mut self.focus, &self.date_filter, entry) && filter.matches(entry) {
+            focus.insert_with_entry(path.clone(), Some(entry));
+        }
+        if self.order.is_sequential() {
+            let key = SortKey::new(self.order, &path, entry);
+            self.sorted.insert(key.clone());
+            self.keys.insert(path.clone(), key);
+        }
+        let group = self.group_of(&path);
+        self.groups[group].insert(path, self.order == PlaybackOrder::Shuffle);
+        true
+    }
+
+    /// Removes an image, or every image below a removed directory, keeping
+    /// the order of the rest.
+    pub fn remove(&mut self, path: &Path) {
+        if let Some(focus) = &mut self.focus { focus.remove(path); }
+        for group in &mut self.groups { group.remove(path); }
+        self.members.retain(|f| !f.starts_with(path));
+        self.ahead.retain(|f| !f.starts_with(path));
+        self.image_weights.retain(|f, _| !f.starts_with(path));
+        let keys = &mut self.keys;
+        self.sorted.retain(|key| {
+            let keep = !key.path.starts_with(path);
+            if !keep { keys.remove(&key.path); }
+            keep
+        });
+    }
+
+    /// Renames an image (or everything below a renamed directory) in place.
+    /// Sequential orders re-sort it under its new name.
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        if let Some(focus) = &mut self.focus { focus.rename(from, to); }
+        for file in self.ahead.iter_mut().filter(|f| f.starts_with(from)) {
+            *file = rebase(file, from, to);
+        }
+        let moved: Vec<PathBuf> = self.image_weights.keys().filter(|f| f.starts_with(from)).cloned().collect();
+        for old in moved {
+            if let Some(weight) = self.image_weights.remove(&old) { self.image_weights.insert(rebase(&old, from, to), weight); }
+        }
+        for file in self.groups.iter_mut().flat_map(|group| group.files.iter_mut()).filter(|f| f.starts_with(from)) {
+            self.members.remove(file.as_path());
+            let renamed = rebase(file, from, to);
+            if let Some(old_key) = self.keys.remove(file.as_path()) {
+                self.sorted.remove(&old_key);
+                // 修改时间和拍摄日期不随改名变化
+                let key = SortKey { rank: old_key.rank, ..SortKey::new(self.order, &renamed, None) };
+                self.sorted.insert(key.clone());
+                self.keys.insert(renamed.clone(), key);
+            }
+            *file = renamed;
+            self.members.insert(file.clone());
+        }
+    }
+
+    pub fn contains(&self, path: &Path) -> bool { self.members.contains(path) }
+
+    /// All images, folder by folder; in shuffle mode in the order they are
+    /// shown this round.
+    pub fn files(&self) -> Vec<PathBuf> {
+        self.groups.iter().flat_map(|group| group.files.iter().cloned()).collect()
+    }
+    pub fn len(&self) -> usize { self.members.len() }
+    pub fn is_empty(&self) -> bool { self.members.is_empty() }
+
+    /// 1-based place of `path` among the images that are being shown (only
+    /// the ones matching the date filter, if any), and their number. The
+    /// place is in playback order for sequential orders and folder by folder
+    /// otherwise.
+    pub fn position(&self, path: &Path) -> Option<(usize, usize)> {
+        if let Some(focus) = self.focus.as_ref().filter(|focus| !focus.is_empty()) {
+            return focus.position(path);
+        }
+        let index = match self.keys.get(path) {
+            Some(key) => self.sorted.range(..key).count(),
+            None => self.groups.iter().flat_map(|group| &group.files).position(|file| file == path)?,
+        };
+        Some((index + 1, self.len()))
+    }
+
+    /// The next `count` images [`Playlist::next_image`] will return, for
+    /// prefetching. Random orders draw them in advance; shuffle stops at the
+    /// end of a round, so the result can be shorter.
+    pub fn upcoming(&mut self, count: usize) -> Vec<PathBuf> {
+        if let Some(focus) = self.focus.as_mut().filter(|focus| !focus.is_empty()) {
+            return focus.upcoming(count);
+        }
+        if self.order.is_sequential() {
+            return self.after_last_key().take(count).map(|key| key.path.clone()).collect();
+        }
+        while self.ahead.len() < count && let Some(path) = self.draw(false) {
+            self.ahead.push_back(path);
+        }
+        self.ahead.iter().take(count).cloned().collect()
+    }
+
+    /// Returns the next image. Shuffle reshuffles a folder once all of its
+    /// images have been shown; sequential orders wrap around to the first image.
+    pub fn next_image(&mut self) -> Option<PathBuf> {
+        if let Some(focus) = self.focus.as_mut().filter(|focus| !focus.is_empty()) {
+            return focus.next_image();
+        }
+        if self.members.is_empty() {
+            return None;
+        }
+        if self.order.is_sequential() {
+            let next = self.after_last_key().next().or_else(|| self.sorted.first()).cloned()?;
+            self.last_key = Some(next.clone());
+            return Some(next.path);
+        }
+        self.ahead.pop_front().or_else(|| self.draw(true))
+    }
+
+    /// Picks a folder by weight and the next image inside it. Without
+    /// `new_round` folders whose round is over are skipped.
+    fn draw(&mut self, new_round: bool) -> Option<PathBuf> {
+        let order = self.order;
+        let candidates: Vec<usize> = (0..self.groups.len()).filter(|i| self.groups[*i].can_draw(order, new_round)).collect();
+        let total: f64 = candidates.iter().map(|i| self.groups[*i].weight).sum();
+        let mut rng = thread_rng();
+        let chosen = if total > 0.0 {
+            let mut pick = rng.gen_range(0.0..total);
+            *candidates.iter().find(|i| { pick -= self.groups[**i].weight; pick < 0.0 }).or(candidates.last())?
+        } else {
+            // 所有权重都为 0 时平均抽取
+            *candidates.choose(&mut rng)?
+        };
+        self.groups[chosen].draw(order, &self.image_weights)
+    }
+
+    /// The configured folder an image belongs to (the innermost one when
+    /// folders are nested), or the catch-all group.
+    fn group_of(&self, path: &Path) -> usize {
+        (1..self.groups.len())
+            .filter(|i| path.starts_with(&self.groups[*i].root))
+            .max_by_key(|i| self.groups[*i].root.components().count())
+            .unwrap_or(0)
+    }
+
+    fn after_last_key(&self) -> impl Iterator<Item = &SortKey> {
+        let start = self.last_key.as_ref().map_or(Bound::Unbounded, Bound::Excluded);
+        self.sorted.range((start, Bound::Unbounded))
+    }
+}
@@ -0,0 +1,94 @@
+//! Favorites, star ratings and hidden images (`photo_widget_ratings.json`).
+//!
+//! Kept next to the configuration rather than in the image index: the index
+//! is a cache that may be deleted at any time, these marks are user data.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::index::rebase;
+
+pub const RATINGS_FILE: &str = "photo_widget_ratings.json";
+
+pub const MAX_STARS: u8 = 5;
+
+/// What the user said about one image.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct ImageRating {
+    #[serde(default)]
+    pub favorite: bool,
+    /// 0 (unrated) to [`MAX_STARS`].
+    #[serde(default)]
+    pub stars: u8,
+    /// Never show this image again.
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+impl ImageRating {
+    /// Relative chance of being picked when higher-rated images are
+    /// favored: unrated images count 1, each star adds 1, favorites double.
+    pub fn playback_weight(&self) -> f64 {
+        let weight = 1.0 + f64::from(self.stars.min(MAX_STARS));
+        if self.favorite { weight * 2.0 } else { weight }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Ratings {
+    entries: HashMap<PathBuf, ImageRating>,
+}
+
+impl Ratings {
+    /// Loads the saved marks, starting empty if the file is missing or unreadable.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path).ok().and_then(|json| serde_json::from_str(&json).ok()).unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) {
+        if let Ok(json) = serde_json::to_string_pretty(self) { let _ = fs::write(path, json); }
+    }
+
+    /// The marks of `path`; unmarked images get the default.
+    pub fn get(&self, path: &Path) -> ImageRating {
+        self.entries.get(path).copied().unwrap_or_default()
+    }
+
+    pub fn is_hidden(&self, path: &Path) -> bool { self.get(path).hidden }
+
+    pub fn hidden(&self) -> HashSet<PathBuf> {
+        self.entries.iter().filter(|(_, rating)| rating.hidden).map(|(path, _)| path.clone()).collect()
+    }
+
+    pub fn update(&mut self, path: &Path, change: impl FnOnce(&mut ImageRating)) {
+        let mut rating = self.get(path);
+        change(&mut rating);
+        rating.stars = rating.stars.min(MAX_STARS);
+        // 恢复默认的条目不再保存
+        if rating == ImageRating::default() {
+            self.entries.remove(path);
+        } else {
+            self.entries.insert(path.to_path_buf(), rating);
+        }
+    }
+
+    /// Makes every hidden image visible again.
+    pub fn unhide_all(&mut self) {
+        for path in self.hidden() { self.update(&path, |rating| rating.hidden = false); }
+    }
+
+    /// Keeps the marks of a renamed file, or of everything below a renamed directory.
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        let moved: Vec<PathBuf> = self.entries.keys().filter(|path| path.starts_with(from)).cloned().collect();
+        for old in moved {
+            if let Some(rating) = self.entries.remove(&old) {
+                self.entries.insert(rebase(&old, from, to), rating);
+            }
+        }
+    }
+}
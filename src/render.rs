@@ -0,0 +1,137 @@
+//! Decoding image files and turning them into something egui can upload.
+
+use std::{path::Path, time::Duration};
+
+use egui::{ColorImage, Vec2};
+use image::{imageops::FilterType, io::Reader as ImageReader, DynamicImage};
+
+use crate::{
+    animation::{self, AnimationSettings},
+    config::ResampleFilter,
+    exif_data::{apply_orientation, apply_quarter_turns, orientation_swaps_axes, ExifData},
+};
+
+impl ResampleFilter {
+    pub fn filter_type(self) -> FilterType {
+        match self {
+            ResampleFilter::Nearest => FilterType::Nearest,
+            ResampleFilter::Triangle => FilterType::Triangle,
+            ResampleFilter::CatmullRom => FilterType::CatmullRom,
+            ResampleFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// What the loader thread needs to know besides the path.
+#[derive(Clone, Debug, Default)]
+pub struct DecodeOptions {
+    /// Clockwise quarter turns on top of the EXIF orientation.
+    pub user_rotation: u8,
+    pub animation: AnimationSettings,
+    /// Size in physical pixels the upright image has to cover; larger images
+    /// are scaled down to it. `None` keeps the native resolution.
+    pub target_size: Option<Vec2>,
+    pub filter: ResampleFilter,
+    /// Also make a blurred background for `FitMode::Letterbox`.
+    pub blurred_background: bool,
+}
+
+/// A picture ready for upload: one frame for stills, every frame for animations.
+pub struct DecodedImage {
+    pub frames: Vec<ColorImage>,
+    /// How long each frame stays up; empty for stills.
+    pub delays: Vec<Duration>,
+    /// The target the frames were scaled down to, `None` at native resolution.
+    pub downscaled_to: Option<Vec2>,
+    /// See [`blurred_background`].
+    pub background: Option<ColorImage>,
+    /// Read for the orientation anyway; kept for the caption.
+    pub exif: Option<ExifData>,
+}
+
+impl DecodedImage {
+    pub fn is_animated(&self) -> bool { self.frames.len() > 1 }
+}
+
+/// Decodes `path` for display. Animated GIF, APNG and WebP files keep all of
+/// their frames (within the configured caps) when animations are enabled.
+/// Frames are scaled down to the target before they are turned upright, so
+/// the rotation only touches the small copy.
+pub fn decode(path: &Path, options: &DecodeOptions) -> Option<DecodedImage> {
+    let exif = ExifData::read(path);
+    let orientation = exif.as_ref().map_or(0, |exif| exif.orientation);
+    let reader = ImageReader::open(path).ok()?.with_guessed_format().ok()?;
+    let animated = match reader.format() {
+        Some(format) if options.animation.enabled => animation::decode_frames(path, format, &options.animation),
+        _ => None,
+    };
+    let (raw_frames, delays) = match animated {
+        Some(frames) => frames.into_iter().map(|frame| (DynamicImage::ImageRgba8(frame.image), frame.delay)).unzip(),
+        None => (vec![reader.decode().ok()?], Vec::new()),
+    };
+
+    // 目标尺寸是按显示方向算的，旋转 90° 的图片在缩放前要交换宽高
+    let target = options.target_size.map(|size| {
+        if orientation_swaps_axes(orientation) != (options.user_rotation % 2 == 1) { egui::vec2(size.y, size.x) } else { size }
+    });
+    let background = options.blurred_background.then(|| {
+        blurred_background(&apply_quarter_turns(apply_orientation(raw_frames[0].thumbnail(BACKGROUND_SIZE, BACKGROUND_SIZE), orientation), options.user_rotation))
+    });
+    let mut downscaled_to = None;
+    let frames = raw_frames
+        .into_iter()
+        .map(|frame| {
+            let frame = match target.and_then(|target| downscaled_size([frame.width(), frame.height()], target)) {
+                Some([width, height]) => {
+                    downscaled_to = options.target_size;
+                    frame.resize_exact(width, height, options.filter.filter_type())
+                }
+                None => frame,
+            };
+            to_color_image(&apply_quarter_turns(apply_orientation(frame, orientation), options.user_rotation))
+        })
+        .collect();
+    Some(DecodedImage { frames, delays, downscaled_to, background, exif })
+}
+
+/// Longest side of the background texture. The GPU stretches it to the
+/// window, which blurs it further.
+const BACKGROUND_SIZE: u32 = 64;
+
+/// A small, blurred and darkened copy of `image` for the bars around it.
+pub fn blurred_background(image: &DynamicImage) -> ColorImage {
+    let mut small = image.thumbnail(BACKGROUND_SIZE, BACKGROUND_SIZE).blur(3.0).to_rgba8();
+    // 压暗到 60%，让前面的图片更突出
+    for pixel in small.pixels_mut() {
+        for channel in &mut pixel.0[..3] { *channel = (f32::from(*channel) * 0.6) as u8; }
+    }
+    to_color_image(&DynamicImage::ImageRgba8(small))
+}
+
+/// Smallest size with the image's aspect ratio that still covers `target`,
+/// or `None` when the image is not larger than that already.
+pub fn downscaled_size(image_size: [u32; 2], target: Vec2) -> Option<[u32; 2]> {
+    let [width, height] = image_size;
+    let scale = (target.x / width as f32).max(target.y / height as f32);
+    if !(scale > 0.0 && scale < 1.0) {
+        return None;
+    }
+    let scaled = |side: u32| ((side as f32 * scale).round() as u32).clamp(1, side);
+    Some([scaled(width), scaled(height)])
+}
+
+/// Decodes an image, guessing the format from its content when the extension
+/// lies, and turns it upright according to its EXIF orientation plus the
+/// user's manual rotation (clockwise quarter turns).
+pub fn decode_image(path: &Path, user_rotation: u8) -> Option<DynamicImage> {
+    let image = ImageReader::open(path).ok()?.with_guessed_format().ok()?.decode().ok()?;
+    let orientation = ExifData::read(path).map_or(0, |exif| exif.orientation);
+    Some(apply_quarter_turns(apply_orientation(image, orientation), user_rotation))
+}
+
+pub fn to_color_image(image: &DynamicImage) -> ColorImage {
+    let size = [image.width() as _, image.height() as _];
+    let image_buffer = image.to_rgba8();
+    let pixels = image_buffer.as_flat_samples();
+    ColorImage::from_rgba_unmultiplied(size, pixels.as_slice())
+}
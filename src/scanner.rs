@@ -0,0 +1,94 @@
+//! Folder scanning on a background thread.
+//!
+//! The UI thread starts a [`Scanner`] and drains it once per frame: found
+//! images arrive in batches as the worker discovers them, and the shared
+//! [`ScanStats`] counters can be shown while the walk is still running.
+
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::{
+    index::ImageIndex,
+    library::{self, ScanFilter, ScanStats},
+};
+
+/// Snapshot of [`ScanStats`] for display.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ScanProgress {
+    pub files_seen: usize,
+    pub images_accepted: usize,
+    pub errors: usize,
+    pub finished: bool,
+}
+
+pub struct Scanner {
+    rx: Receiver<PathBuf>,
+    stats: Arc<ScanStats>,
+    cancel: Arc<AtomicBool>,
+    finished: bool,
+}
+
+impl Scanner {
+    /// Spawns a worker that walks `folders`. The index is updated in place
+    /// and pruned once the walk completes without being cancelled.
+    pub fn start(folders: Vec<PathBuf>, filter: ScanFilter, index: Arc<Mutex<ImageIndex>>) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let stats = Arc::new(ScanStats::default());
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let worker_stats = stats.clone();
+        let worker_cancel = cancel.clone();
+        thread::spawn(move || {
+            library::scan_folders(&folders, &filter, &index, &worker_stats, &worker_cancel, |path| {
+                // 接收端已被丢弃 (新的扫描取代了这一次)，停止遍历
+                if tx.send(path).is_err() { worker_cancel.store(true, Ordering::Relaxed); }
+            });
+            // tx 在这里被丢弃，接收端据此得知扫描已结束
+        });
+
+        Self { rx, stats, cancel, finished: false }
+    }
+
+    /// Stops the worker at the next file. Images already found stay queued.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Images found since the last call. Never blocks.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let mut found = Vec::new();
+        loop {
+            match self.rx.try_recv() {
+                Ok(path) => found.push(path),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => { self.finished = true; break; }
+            }
+        }
+        found
+    }
+
+    /// `true` once the worker has exited and everything it found was polled.
+    pub fn is_finished(&self) -> bool { self.finished }
+
+    pub fn progress(&self) -> ScanProgress {
+        ScanProgress {
+            files_seen: self.stats.files_seen.load(Ordering::Relaxed),
+            images_accepted: self.stats.images_accepted.load(Ordering::Relaxed),
+            errors: self.stats.errors.load(Ordering::Relaxed),
+            finished: self.finished,
+        }
+    }
+}
+
+impl Drop for Scanner {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
@@ -0,0 +1,141 @@
+//! Animated transitions from one image to the next.
+//!
+//! The widget keeps the outgoing texture alive while a transition runs; this
+//! module only works out, for a point in time, which part of each texture is
+//! drawn where and how opaque it is.
+
+use std::time::Duration;
+
+use egui::{pos2, vec2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::FitMode, layout};
+
+/// The dissolve reveals the new image in this many cells per side.
+pub const DISSOLVE_GRID: usize = 16;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TransitionKind {
+    /// Switch instantly.
+    None,
+    #[default]
+    Crossfade,
+    /// The new image pushes the old one out of the window.
+    Slide,
+    /// The old image grows while the new one fades in and settles over it.
+    ZoomFade,
+    /// The new image appears in random blocks.
+    Dissolve,
+}
+
+/// Where the new image slides in from.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SlideDirection {
+    #[default]
+    Right,
+    Left,
+    Top,
+    Bottom,
+}
+
+impl SlideDirection {
+    /// Direction both images move in.
+    fn motion(self) -> Vec2 {
+        match self {
+            SlideDirection::Right => vec2(-1.0, 0.0),
+            SlideDirection::Left => vec2(1.0, 0.0),
+            SlideDirection::Top => vec2(0.0, 1.0),
+            SlideDirection::Bottom => vec2(0.0, -1.0),
+        }
+    }
+}
+
+/// Transition settings (`transition` in the config).
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct TransitionSettings {
+    pub kind: TransitionKind,
+    pub duration_ms: u32,
+    pub direction: SlideDirection,
+}
+
+impl Default for TransitionSettings {
+    fn default() -> Self {
+        Self { kind: TransitionKind::Crossfade, duration_ms: 600, direction: SlideDirection::Right }
+    }
+}
+
+impl TransitionSettings {
+    pub fn duration(&self) -> Duration { Duration::from_millis(u64::from(self.duration_ms)) }
+
+    pub fn is_enabled(&self) -> bool { self.kind != TransitionKind::None && self.duration_ms > 0 }
+
+    /// Eased progress from 0 to 1, `elapsed` into the transition.
+    pub fn progress(&self, elapsed: Duration) -> f32 {
+        if self.duration_ms == 0 { return 1.0; }
+        let t = (elapsed.as_secs_f32() / self.duration().as_secs_f32()).clamp(0.0, 1.0);
+        // 两端放慢 (smoothstep)
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// The layers to paint, outgoing image first, `progress` of the way from
+    /// `outgoing` to `incoming`. Both start out placed in the window as they
+    /// would be without a transition.
+    pub fn layers(&self, progress: f32, window: Rect, outgoing: Layer, incoming: Layer) -> (Vec<Layer>, Vec<Layer>) {
+        let t = progress.clamp(0.0, 1.0);
+        match self.kind {
+            TransitionKind::None => (Vec::new(), vec![incoming]),
+            // 旧图保持不透明，新图在上面淡入，避免中途透出桌面
+            TransitionKind::Crossfade => (vec![outgoing], vec![incoming.faded(t)]),
+            TransitionKind::Slide => {
+                let offset = self.direction.motion() * window.size();
+                (vec![outgoing.translated(offset * t)], vec![incoming.translated(offset * (t - 1.0))])
+            }
+            TransitionKind::ZoomFade => (vec![outgoing.scaled(1.0 + 0.15 * t)], vec![incoming.scaled(0.95 + 0.05 * t).faded(t)]),
+            TransitionKind::Dissolve => (vec![outgoing], dissolve_cells(t).map(|(cell, opacity)| incoming.cell(cell).faded(opacity)).collect()),
+        }
+    }
+}
+
+/// One image in the window: where it goes, which part of the texture is
+/// visible there and how opaque it is.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Layer {
+    pub rect: Rect,
+    pub uv: Rect,
+    pub opacity: f32,
+}
+
+impl Layer {
+    /// An image placed in `window` according to the fit mode.
+    pub fn placed(fit_mode: FitMode, texture_size: Vec2, window: Rect) -> Self {
+        let (rect, uv) = layout::image_placement(fit_mode, texture_size, window);
+        Self { rect, uv, opacity: 1.0 }
+    }
+
+    fn faded(self, opacity: f32) -> Self { Self { opacity: self.opacity * opacity, ..self } }
+
+    fn translated(self, offset: Vec2) -> Self { Self { rect: self.rect.translate(offset), ..self } }
+
+    fn scaled(self, factor: f32) -> Self { Self { rect: Rect::from_center_size(self.rect.center(), self.rect.size() * factor), ..self } }
+
+    /// The part of the layer inside `cell`, given in 0..1 of its rect.
+    fn cell(self, cell: Rect) -> Self {
+        let within = |area: Rect| Rect::from_min_max(area.lerp_inside(cell.min.to_vec2()), area.lerp_inside(cell.max.to_vec2()));
+        Self { rect: within(self.rect), uv: within(self.uv), ..self }
+    }
+}
+
+/// The dissolve cells that have started to appear, in 0..1 coordinates, with
+/// their opacity. Every cell is fully opaque at the end.
+fn dissolve_cells(progress: f32) -> impl Iterator<Item = (Rect, f32)> {
+    let size = 1.0 / DISSOLVE_GRID as f32;
+    (0..DISSOLVE_GRID * DISSOLVE_GRID).filter_map(move |i| {
+        // 每格固定的伪随机出现时间，落在前 80% 的时长里，再用 20% 淡入
+        let start = (i as u32).wrapping_mul(2_654_435_761) >> 8;
+        let start = start as f32 / (1 << 24) as f32 * 0.8;
+        let opacity = ((progress - start) / 0.2).clamp(0.0, 1.0);
+        let min = pos2((i % DISSOLVE_GRID) as f32 * size, (i / DISSOLVE_GRID) as f32 * size);
+        (opacity > 0.0).then(|| (Rect::from_min_size(min, vec2(size, size)), opacity))
+    })
+}
@@ -0,0 +1,216 @@
+//! Live updates of the image list while the widget is running.
+//!
+//! A [`FolderWatcher`] subscribes to the configured folders (inotify on Linux,
+//! the native API elsewhere, or periodic polling for network shares where
+//! change notifications do not work) and turns raw filesystem events into
+//! [`LibraryChange`]s. New files are probed on the watcher's thread, so the UI
+//! thread only has to apply the result.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::AtomicBool,
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher,
+};
+
+use crate::{
+    config::WatchMode,
+    index::ImageIndex,
+    library::{self, ScanFilter, ScanStats},
+};
+
+/// How often the polling backend rescans the watched folders.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long the first half of a rename waits for its second half before the
+/// file counts as moved out of the watched folders.
+pub const RENAME_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LibraryChange {
+    /// A new (or newly readable) image that passes the filters.
+    Added(PathBuf),
+    /// A file or a whole directory that is gone or no longer passes the filters.
+    Removed(PathBuf),
+    /// A file or directory that moved within the watched folders.
+    Renamed { from: PathBuf, to: PathBuf },
+}
+
+pub struct FolderWatcher {
+    // 只需要保持 watcher 存活；丢弃它即停止监视
+    _watcher: Box<dyn Watcher + Send>,
+    rx: Receiver<LibraryChange>,
+    moves: Arc<Mutex<Vec<PendingMove>>>,
+    index: Arc<Mutex<ImageIndex>>,
+}
+
+/// The first half of a rename, waiting for the event with the new name.
+struct PendingMove {
+    path: PathBuf,
+    // inotify 的 cookie；Windows 不提供，此时按顺序配对
+    tracker: Option<usize>,
+    since: Instant,
+}
+
+impl FolderWatcher {
+    /// Starts watching `folders`. `WatchMode::Native` falls back to polling
+    /// when the OS watcher cannot be created (e.g. the inotify watch limit is
+    /// exhausted). Returns `None` for `WatchMode::Off` or when no folder could
+    /// be watched at all.
+    pub fn start(mode: WatchMode, folders: &[PathBuf], filter: ScanFilter, index: Arc<Mutex<ImageIndex>>) -> Option<Self> {
+        let (tx, rx) = mpsc::channel();
+        let moves = Arc::new(Mutex::new(Vec::new()));
+        let handler = EventTranslator { tx, filter, index: index.clone(), moves: moves.clone() };
+        let mut watcher: Box<dyn Watcher + Send> = match mode {
+            WatchMode::Off => return None,
+            WatchMode::Native => match RecommendedWatcher::new(handler.clone(), Config::default()) {
+                Ok(watcher) => Box::new(watcher),
+                Err(_) => Box::new(PollWatcher::new(handler, Config::default().with_poll_interval(POLL_INTERVAL)).ok()?),
+            },
+            WatchMode::Polling => Box::new(PollWatcher::new(handler, Config::default().with_poll_interval(POLL_INTERVAL)).ok()?),
+        };
+
+        let mut watching = false;
+        for folder in folders {
+            watching |= watcher.watch(folder, RecursiveMode::Recursive).is_ok();
+        }
+        watching.then(|| Self { _watcher: watcher, rx, moves, index })
+    }
+
+    /// Changes since the last call. Never blocks.
+    pub fn poll(&self) -> Vec<LibraryChange> {
+        let mut changes: Vec<LibraryChange> = self.rx.try_iter().collect();
+        // 等不到新名字的改名：文件被移出了监视的文件夹
+        let mut moves = self.moves.lock().unwrap();
+        let (expired, waiting) = std::mem::take(&mut *moves).into_iter().partition(|pending| pending.since.elapsed() >= RENAME_TIMEOUT);
+        *moves = waiting;
+        for PendingMove { path, .. } in expired {
+            self.index.lock().unwrap().prune(std::slice::from_ref(&path), &Default::default());
+            changes.push(LibraryChange::Removed(path));
+        }
+        changes
+    }
+}
+
+/// Runs on the watcher's thread: probes files and reports what changed.
+#[derive(Clone)]
+struct EventTranslator {
+    tx: Sender<LibraryChange>,
+    filter: ScanFilter,
+    index: Arc<Mutex<ImageIndex>>,
+    moves: Arc<Mutex<Vec<PendingMove>>>,
+}
+
+impl notify::EventHandler for EventTranslator {
+    fn handle_event(&mut self, event: notify::Result<Event>) {
+        let Ok(event) = event else { return };
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                self.moves.lock().unwrap().retain(|pending| pending.path != event.paths[0]);
+                self.renamed(&event.paths[0], &event.paths[1]);
+            }
+            // 改名的前一半：先记下来，等新名字到了再当作改名处理
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                let mut moves = self.moves.lock().unwrap();
+                moves.extend(event.paths.iter().map(|path| PendingMove { path: path.clone(), tracker: event.tracker(), since: Instant::now() }));
+            }
+            // 改名的后一半：inotify 紧接着会发 Both，这里不用处理；Windows 没有 Both，按顺序配对
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) if event.paths.len() == 1 => {
+                let to = &event.paths[0];
+                match event.tracker() {
+                    Some(tracker) if self.moves.lock().unwrap().iter().any(|pending| pending.tracker == Some(tracker)) => {}
+                    tracker => match self.take_move(|pending| tracker.is_none() && pending.tracker.is_none()) {
+                        Some(from) => self.renamed(&from, to),
+                        // 从监视范围外移入
+                        None => self.changed(to, true),
+                    },
+                }
+            }
+            EventKind::Remove(_) => {
+                for path in &event.paths { self.removed(path); }
+            }
+            // 新建或移入：可能是整个目录
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)) => {
+                for path in &event.paths { self.changed(path, true); }
+            }
+            // 写入内容：文件可能刚创建还没写完，所以写入事件也要重新探测
+            EventKind::Modify(_) => {
+                for path in &event.paths { self.changed(path, false); }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl EventTranslator {
+    fn send(&self, change: LibraryChange) {
+        let _ = self.tx.send(change);
+    }
+
+    /// Removes the latest pending move `matches` accepts and returns its path.
+    fn take_move(&self, matches: impl Fn(&PendingMove) -> bool) -> Option<PathBuf> {
+        let mut moves = self.moves.lock().unwrap();
+        let position = moves.iter().rposition(matches)?;
+        Some(moves.remove(position).path)
+    }
+
+    fn removed(&self, path: &Path) {
+        self.index.lock().unwrap().prune(&[path.to_path_buf()], &Default::default());
+        self.send(LibraryChange::Removed(path.to_path_buf()));
+    }
+
+    fn renamed(&self, from: &Path, to: &Path) {
+        // 条目随文件移动，改名不会丢掉旋转、焦点和哈希 (修改时间和大小不变，缓存仍然有效)
+        self.index.lock().unwrap().rename(from, to);
+        if to.is_dir() {
+            self.send(LibraryChange::Renamed { from: from.to_path_buf(), to: to.to_path_buf() });
+        } else if self.accepts(to) {
+            self.send(LibraryChange::Renamed { from: from.to_path_buf(), to: to.to_path_buf() });
+            // 源文件可能不在列表中 (例如下载完成后从 .part 改名)，此时按新增处理
+            self.send(LibraryChange::Added(to.to_path_buf()));
+        } else {
+            // 改名成了不支持的扩展名，相当于删除
+            self.removed(from);
+        }
+    }
+
+    fn changed(&self, path: &Path, walk_dirs: bool) {
+        if path.is_dir() {
+            if !walk_dirs {
+                return;
+            }
+            // 整个目录被移入：只遍历这个目录
+            let stats = ScanStats::default();
+            library::scan_folders(&[path.to_path_buf()], &self.filter, &self.index, &stats, &AtomicBool::new(false), |found| {
+                self.send(LibraryChange::Added(found));
+            });
+        } else if self.accepts(path) {
+            self.send(LibraryChange::Added(path.to_path_buf()));
+        } else if !path.exists() || self.filter.is_candidate(path) {
+            // 文件被改写后不再符合筛选条件 (或无法解码)
+            self.send(LibraryChange::Removed(path.to_path_buf()));
+        }
+    }
+
+    fn accepts(&self, path: &Path) -> bool {
+        self.filter.is_candidate(path)
+            && !self.filter.is_hidden(path)
+            && self.filter.in_scope(path)
+            && library::probe_shared(&self.index, path).is_some_and(|info| {
+                let accepted = self.filter.accepts(&info);
+                if accepted {
+                    library::hash_shared(&self.index, path, &info, &self.filter.duplicates);
+                    library::focus_shared(&self.index, path, &info, self.filter.smart_crop);
+                }
+                accepted
+            })
+    }
+}
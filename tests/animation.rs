@@ -0,0 +1,58 @@
+use std::{fs::File, path::Path, time::Duration};
+
+use desktop_widget::{
+    animation::{self, AnimationClock, AnimationSettings, DEFAULT_FRAME_DELAY},
+    render::{self, DecodeOptions},
+};
+use image::{codecs::gif::GifEncoder, Delay, Frame, ImageFormat, RgbaImage};
+
+fn write_gif(path: &Path, delays_ms: &[u32]) {
+    let mut encoder = GifEncoder::new(File::create(path).unwrap());
+    let frames = delays_ms.iter().enumerate().map(|(i, ms)| {
+        let image = RgbaImage::from_pixel(8, 6, image::Rgba([(i * 25) as u8, 0, 0, 255]));
+        Frame::from_parts(image, 0, 0, Delay::from_numer_denom_ms(*ms, 1))
+    });
+    encoder.encode_frames(frames).unwrap();
+}
+
+#[test]
+fn gif_frames_keep_their_delays() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("anim.gif");
+    // 0 ms 的帧按浏览器的习惯改成 100 ms
+    write_gif(&path, &[50, 0, 200]);
+
+    let decoded = render::decode(&path, &DecodeOptions { user_rotation: 1, ..DecodeOptions::default() }).unwrap();
+    assert!(decoded.is_animated());
+    assert_eq!(decoded.delays, vec![Duration::from_millis(50), DEFAULT_FRAME_DELAY, Duration::from_millis(200)]);
+    // 手动旋转对每一帧都生效
+    assert!(decoded.frames.iter().all(|frame| frame.size == [6, 8]));
+
+    let disabled = DecodeOptions { animation: AnimationSettings { enabled: false, ..AnimationSettings::default() }, ..DecodeOptions::default() };
+    assert_eq!(render::decode(&path, &disabled).unwrap().frames.len(), 1);
+}
+
+#[test]
+fn frame_and_memory_caps_truncate_the_animation() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("long.gif");
+    write_gif(&path, &[40; 10]);
+
+    let few_frames = AnimationSettings { max_frames: 4, ..AnimationSettings::default() };
+    assert_eq!(animation::decode_frames(&path, ImageFormat::Gif, &few_frames).unwrap().len(), 4);
+
+    // 预算为 0 时只保留第一帧，也就不再算作动图
+    let no_memory = AnimationSettings { max_memory_mb: 0, ..AnimationSettings::default() };
+    assert!(animation::decode_frames(&path, ImageFormat::Gif, &no_memory).is_none());
+}
+
+#[test]
+fn clock_loops_and_reports_the_next_frame() {
+    let clock = AnimationClock::new(vec![Duration::from_millis(100), Duration::from_millis(300)]);
+    assert_eq!(clock.loop_duration(), Duration::from_millis(400));
+    assert_eq!(clock.frame_at(Duration::ZERO), (0, 0));
+    assert_eq!(clock.frame_at(Duration::from_millis(150)), (1, 0));
+    assert_eq!(clock.frame_at(Duration::from_millis(450)), (0, 1));
+    assert_eq!(clock.until_next_frame(Duration::from_millis(150)), Some(Duration::from_millis(250)));
+    assert_eq!(AnimationClock::new(Vec::new()).frame_at(Duration::from_secs(1)), (0, 0));
+}
@@ -0,0 +1,34 @@
+use std::path::Path;
+
+use desktop_widget::cache::ImageCache;
+
+#[test]
+fn least_recently_used_entries_are_evicted_first() {
+    let mut cache = ImageCache::new(100);
+    cache.insert("a.jpg".into(), 'a', 40);
+    cache.insert("b.jpg".into(), 'b', 40);
+    // 访问 a 之后，b 成了最久未使用的
+    assert_eq!(cache.get(Path::new("a.jpg")), Some(&'a'));
+    cache.insert("c.jpg".into(), 'c', 40);
+
+    assert!(!cache.contains(Path::new("b.jpg")));
+    assert!(cache.contains(Path::new("a.jpg")) && cache.contains(Path::new("c.jpg")));
+    assert_eq!(cache.used_bytes(), 80);
+
+    // 单个超出预算的条目仍然保留
+    cache.insert("huge.jpg".into(), 'h', 500);
+    assert_eq!((cache.len(), cache.used_bytes()), (1, 500));
+}
+
+#[test]
+fn removing_a_directory_drops_its_images() {
+    let mut cache = ImageCache::new(1000);
+    cache.insert("trip/1.jpg".into(), 1, 10);
+    cache.insert("trip/2.jpg".into(), 2, 10);
+    cache.insert("home.jpg".into(), 3, 10);
+
+    cache.remove_under(Path::new("trip"));
+    assert_eq!((cache.len(), cache.used_bytes()), (1, 10));
+    assert_eq!(cache.remove(Path::new("home.jpg")), Some(3));
+    assert!(cache.is_empty());
+}
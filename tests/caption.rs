@@ -0,0 +1,60 @@
+use std::path::{Path, PathBuf};
+
+use desktop_widget::{
+    caption::CaptionInfo,
+    config::PlaybackOrder,
+    exif_data::{CaptureDate, ExifData},
+    playlist::Playlist,
+};
+
+fn info() -> CaptionInfo {
+    let exif = ExifData {
+        capture_date: Some(CaptureDate { year: 2021, month: 6, day: 5, hour: 9, minute: 3, second: 0 }),
+        camera: Some("Canon EOS R5".to_string()),
+        ..ExifData::default()
+    };
+    CaptionInfo::new(Path::new("/photos/Trips/beach.jpg"), None, Some(&exif), Some((3, 12)), "Travel", 0)
+}
+
+#[test]
+fn placeholders_are_filled_in() {
+    let caption = info();
+    assert_eq!(caption.render("{filename} ({folder})\n{date} · {camera}"), "beach.jpg (Trips)\n2021-06-05 09:03 · Canon EOS R5");
+    assert_eq!(caption.render("{index}/{total} in {album}"), "3/12 in Travel");
+    // 未知的占位符和不完整的括号原样保留
+    assert_eq!(caption.render("{unknown} {filename"), "{unknown} {filename");
+}
+
+#[test]
+fn empty_placeholders_leave_no_clutter() {
+    let caption = info();
+    assert_eq!(caption.render("{filename}\n{lens}\n{exposure} · {lens}"), "beach.jpg");
+    assert_eq!(caption.render("{date} · {lens}"), "2021-06-05 09:03");
+    assert_eq!(caption.render("{lens} | {camera}"), "Canon EOS R5");
+    assert_eq!(caption.render("Photo frame\n{lens}"), "Photo frame");
+    assert_eq!(caption.render("{camera} · {lens} · {date}"), "Canon EOS R5 · 2021-06-05 09:03");
+}
+
+#[test]
+fn values_are_kept_as_they_are() {
+    let caption = CaptionInfo::new(Path::new("/home/me/-trip-/IMG-.jpg"), None, None, None, "", 0);
+    assert_eq!(caption.render("{path}"), "/home/me/-trip-/IMG-.jpg");
+    let caption = CaptionInfo::new(Path::new("/photos/draft-"), None, None, None, "", 0);
+    assert_eq!(caption.render("{filename} · {camera}"), "draft-");
+    assert_eq!(caption.render("{camera} - {filename}"), "draft-");
+}
+
+#[test]
+fn position_counts_in_playback_order() {
+    let files: Vec<PathBuf> = ["c.jpg", "a.jpg", "b.jpg"].iter().map(PathBuf::from).collect();
+    let mut playlist = Playlist::default();
+    playlist.set_order(PlaybackOrder::ByName);
+    playlist.set_files(files.clone());
+    assert_eq!(playlist.position(Path::new("b.jpg")), Some((2, 3)));
+    assert_eq!(playlist.position(Path::new("missing.jpg")), None);
+
+    playlist.set_order(PlaybackOrder::Shuffle);
+    playlist.set_files(files);
+    let (index, total) = playlist.position(Path::new("c.jpg")).unwrap();
+    assert!((1..=3).contains(&index) && total == 3);
+}
@@ -0,0 +1,89 @@
+use std::path::PathBuf;
+
+use desktop_widget::config::{load_config_from, save_config_to, AppConfig, FitMode, FolderEntry, ImageOrientationFilter, TimeUnit};
+
+#[test]
+fn config_round_trips_through_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    let config = AppConfig { fit_mode: FitMode::Contain, window_pos: Some((12.0, 34.0)), ..AppConfig::default() };
+
+    save_config_to(&path, &config);
+    let loaded = load_config_from(&path).unwrap();
+
+    assert_eq!(loaded.fit_mode, FitMode::Contain);
+    assert_eq!(loaded.window_pos, Some((12.0, 34.0)));
+}
+
+#[test]
+fn old_config_without_window_pos_still_loads() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    let mut json: serde_json::Value = serde_json::to_value(AppConfig::default()).unwrap();
+    json.as_object_mut().unwrap().remove("window_pos");
+    std::fs::write(&path, json.to_string()).unwrap();
+
+    assert_eq!(load_config_from(&path).unwrap().window_pos, None);
+}
+
+#[test]
+fn refresh_interval_is_split_into_largest_unit() {
+    let mut config = AppConfig { refresh_interval: 7200, ..AppConfig::default() };
+    config.sync_refresh_unit();
+    assert_eq!((config.refresh_value, config.refresh_unit), (2, TimeUnit::Hours));
+
+    config.refresh_interval = 90;
+    config.sync_refresh_unit();
+    assert_eq!((config.refresh_value, config.refresh_unit), (90, TimeUnit::Seconds));
+
+    config.refresh_value = 3;
+    config.refresh_unit = TimeUnit::Minutes;
+    config.apply_refresh_value();
+    assert_eq!(config.refresh_interval, 180);
+}
+
+#[test]
+fn plain_folder_paths_migrate_to_weighted_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    let mut json: serde_json::Value = serde_json::to_value(AppConfig::default()).unwrap();
+    json["folders"] = serde_json::json!(["/photos/family", { "path": "/photos/all", "weight": 0.5, "enabled": false }]);
+    std::fs::write(&path, json.to_string()).unwrap();
+
+    let config = load_config_from(&path).unwrap();
+    assert_eq!(config.folders, vec![
+        FolderEntry::new("/photos/family".into()),
+        FolderEntry { weight: 0.5, enabled: false, ..FolderEntry::new("/photos/all".into()) },
+    ]);
+    assert_eq!(config.enabled_folders(), vec![PathBuf::from("/photos/family")]);
+
+    // 保存后写出的是新格式，再次读取结果不变
+    save_config_to(&path, &config);
+    assert_eq!(load_config_from(&path).unwrap().folders, config.folders);
+}
+
+#[test]
+fn albums_keep_their_own_folders_and_filters() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    let mut config = AppConfig { folders: vec![FolderEntry::new(PathBuf::from("family"))], ..AppConfig::default() };
+
+    assert!(config.add_album("Travel"));
+    assert!(!config.add_album("Default"));
+    config.folders.push(FolderEntry::new(PathBuf::from("trips")));
+    config.orientation_filter = ImageOrientationFilter::Landscape;
+    assert!(config.rename_album("Travel 2023"));
+    assert_eq!(config.album_names(), ["Default", "Travel 2023"]);
+
+    save_config_to(&path, &config);
+    let mut loaded = load_config_from(&path).unwrap();
+    assert_eq!(loaded.active_album, "Travel 2023");
+    assert!(loaded.switch_album("Default"));
+    assert_eq!(loaded.folders[0].path, PathBuf::from("family"));
+    assert_eq!(loaded.orientation_filter, ImageOrientationFilter::Both);
+
+    assert!(loaded.remove_album());
+    assert_eq!(loaded.active_album, "Travel 2023");
+    assert_eq!(loaded.folders[0].path, PathBuf::from("trips"));
+    assert!(!loaded.remove_album());
+}
@@ -0,0 +1,64 @@
+use std::{
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use desktop_widget::{
+    decoder::{DecodePool, DecodeResult},
+    render::DecodeOptions,
+};
+
+fn write_png(path: &Path, width: u32, height: u32) {
+    image::RgbImage::new(width, height).save(path).unwrap();
+}
+
+/// Collects results until nothing is pending any more.
+fn drain(pool: &DecodePool) -> Vec<DecodeResult> {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut results = Vec::new();
+    while pool.pending() > 0 && Instant::now() < deadline {
+        results.extend(pool.poll());
+        thread::sleep(Duration::from_millis(5));
+    }
+    results
+}
+
+#[test]
+fn results_carry_their_request_id() {
+    let dir = tempfile::tempdir().unwrap();
+    let (a, b) = (dir.path().join("a.png"), dir.path().join("b.png"));
+    write_png(&a, 4, 3);
+    write_png(&b, 3, 4);
+
+    let mut pool = DecodePool::new(2);
+    let id_a = pool.request(a.clone(), DecodeOptions::default(), false);
+    let id_b = pool.request(b.clone(), DecodeOptions::default(), true);
+    let broken = pool.request(dir.path().join("missing.png"), DecodeOptions::default(), false);
+
+    let results = drain(&pool);
+    assert_eq!(results.len(), 3);
+    for result in results {
+        let size = result.image.as_ref().map(|image| image.frames[0].size);
+        match result.id {
+            id if id == id_a => assert_eq!((result.path.as_path(), size), (a.as_path(), Some([4, 3]))),
+            id if id == id_b => assert_eq!((result.path.as_path(), size), (b.as_path(), Some([3, 4]))),
+            id => assert_eq!((id, size), (broken, None)),
+        }
+    }
+}
+
+#[test]
+fn cancelled_requests_are_never_delivered() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("photo.png");
+    write_png(&path, 64, 64);
+
+    let mut pool = DecodePool::new(1);
+    let ids: Vec<_> = (0..20).map(|_| pool.request(path.clone(), DecodeOptions::default(), false)).collect();
+    // 模拟连续点击：只有最后一次请求仍然有效
+    for id in &ids[..19] { pool.cancel(*id); }
+
+    let delivered: Vec<_> = drain(&pool).into_iter().map(|result| result.id).collect();
+    assert_eq!(delivered, vec![ids[19]]);
+}
@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+
+use desktop_widget::{
+    duplicates::{self, Admission, DuplicateSettings, Duplicates},
+    index::ImageIndex,
+    library::{self, ScanFilter},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+fn write_gradient(path: &Path, width: u32, height: u32, flip: bool) {
+    let image = image::RgbImage::from_fn(width, height, |x, y| {
+        let value = ((x * 255 / width + y * 64 / height) % 256) as u8;
+        image::Rgb([if flip { 255 - value } else { value }; 3])
+    });
+    image.save(path).unwrap();
+}
+
+#[test]
+fn near_duplicates_are_similar_and_other_images_are_not() {
+    let dir = tempfile::tempdir().unwrap();
+    write_gradient(&dir.path().join("large.png"), 128, 96, false);
+    write_gradient(&dir.path().join("small.png"), 64, 48, false);
+    write_gradient(&dir.path().join("other.png"), 128, 96, true);
+    let hash = |name: &str| duplicates::perceptual_hash(&dir.path().join(name)).unwrap();
+
+    assert!(duplicates::hash_distance(hash("large.png"), hash("small.png")) <= 4);
+    assert!(duplicates::hash_distance(hash("large.png"), hash("other.png")) > 16);
+    assert_ne!(duplicates::content_hash(&dir.path().join("large.png")), duplicates::content_hash(&dir.path().join("small.png")));
+}
+
+#[test]
+fn only_the_best_copy_of_each_photo_is_shown() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("backup")).unwrap();
+    write_gradient(&dir.path().join("backup/photo.png"), 64, 48, false);
+    std::fs::copy(dir.path().join("backup/photo.png"), dir.path().join("photo.png")).unwrap();
+    write_gradient(&dir.path().join("export.png"), 128, 96, false);
+    write_gradient(&dir.path().join("other.png"), 64, 48, true);
+    let settings = DuplicateSettings { enabled: true, near_duplicates: true, ..DuplicateSettings::default() };
+    let filter = ScanFilter { duplicates: settings, ..ScanFilter::default() };
+    let mut index = ImageIndex::default();
+    let mut found = library::scan_image_files(&[dir.path().to_path_buf()], &filter, &mut index);
+    found.sort();
+
+    let mut duplicates = Duplicates::new(settings);
+    let mut shown: Vec<PathBuf> = Vec::new();
+    for path in found {
+        match duplicates.add(path.clone(), index.get(&path).unwrap()) {
+            Admission::Show => shown.push(path),
+            Admission::Skip => {}
+            Admission::Replace(worse) => { shown.retain(|p| *p != worse); shown.push(path); }
+        }
+    }
+    shown.sort();
+    // 完全相同的副本和缩小的版本都归入分辨率最高的 export.png
+    assert_eq!(shown, [dir.path().join("export.png"), dir.path().join("other.png")]);
+    assert_eq!((duplicates.groups().len(), duplicates.skipped()), (1, 2));
+    assert_eq!(duplicates.groups()[0][0], dir.path().join("export.png"));
+
+    // 删除显示的那张后由次好的副本顶上
+    assert_eq!(duplicates.remove(&dir.path().join("export.png")), [dir.path().join("backup/photo.png")]);
+    assert_eq!(duplicates.skipped(), 1);
+}
+
+#[test]
+fn undecodable_images_are_hashed_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("truncated.png");
+    write_gradient(&path, 64, 48, false);
+    // 文件头完整 (能读出尺寸)，图像数据被截断
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+    let settings = DuplicateSettings { enabled: true, near_duplicates: true, ..DuplicateSettings::default() };
+    let mut index = ImageIndex::default();
+
+    library::scan_image_files(&[dir.path().to_path_buf()], &ScanFilter { duplicates: settings, ..ScanFilter::default() }, &mut index);
+    let entry = index.get(&path).unwrap();
+    assert_eq!(entry.perceptual_hash, None);
+    assert!(entry.perceptual_hash_tried && entry.content_hash.is_some());
+}
+
+#[test]
+fn near_duplicates_are_found_among_many_images() {
+    let dir = tempfile::tempdir().unwrap();
+    write_gradient(&dir.path().join("photo.png"), 64, 48, false);
+    let mut index = ImageIndex::default();
+    library::scan_image_files(&[dir.path().to_path_buf()], &ScanFilter::default(), &mut index);
+    let entry = index.get(&dir.path().join("photo.png")).unwrap().clone();
+    let with_hash = |hash: u64| {
+        let mut entry = entry.clone();
+        entry.perceptual_hash = Some(hash);
+        entry
+    };
+
+    // 随机的 64 位哈希彼此相差很远，只有改动了几位的副本才算重复
+    let mut rng = StdRng::seed_from_u64(7);
+    let hashes: Vec<u64> = (0..2000).map(|_| rng.gen_range(0..=u64::MAX)).collect();
+    let mut duplicates = Duplicates::new(DuplicateSettings { enabled: true, near_duplicates: true, max_distance: 4 });
+    for (i, hash) in hashes.iter().enumerate() {
+        assert_eq!(duplicates.add(format!("/photos/{i}.png").into(), &with_hash(*hash)), Admission::Show);
+    }
+    for (i, hash) in hashes.iter().enumerate() {
+        assert_eq!(duplicates.add(format!("/photos/copies/{i}.png").into(), &with_hash(hash ^ 0b1011)), Admission::Skip);
+    }
+    assert_eq!((duplicates.groups().len(), duplicates.skipped()), (2000, 2000));
+}
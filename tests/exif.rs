@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use desktop_widget::{
+    config::{ImageOrientationFilter, PlaybackOrder},
+    exif_data::{CaptureDate, ExifData},
+    index::ImageIndex,
+    playlist::Playlist,
+    library::{self, ScanFilter},
+    render::{self, DecodeOptions},
+};
+
+/// Writes a JPEG whose EXIF block carries the given orientation tag.
+fn write_jpeg_with_orientation(path: &Path, width: u32, height: u32, orientation: u16) {
+    // APP1: "Exif\0\0" + 大端 TIFF 头 + 只有一个 Orientation (0x0112, SHORT) 条目的 IFD0
+    let mut tiff = vec![b'M', b'M', 0, 42, 0, 0, 0, 8, 0, 1, 0x01, 0x12, 0, 3, 0, 0, 0, 1];
+    tiff.extend_from_slice(&orientation.to_be_bytes());
+    tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+    write_jpeg_with_tiff(path, width, height, &tiff);
+}
+
+/// Writes a JPEG whose EXIF block only has a `DateTime` ("YYYY:MM:DD hh:mm:ss").
+fn write_jpeg_with_date(path: &Path, date: &str) {
+    // IFD0 只有一个 DateTime (0x0132, ASCII, 20 字节) 条目，数据紧跟在 IFD 之后 (偏移 26)
+    let mut tiff = vec![b'M', b'M', 0, 42, 0, 0, 0, 8, 0, 1, 0x01, 0x32, 0, 2, 0, 0, 0, 20, 0, 0, 0, 26, 0, 0, 0, 0];
+    tiff.extend_from_slice(date.as_bytes());
+    tiff.push(0);
+    write_jpeg_with_tiff(path, 8, 8, &tiff);
+}
+
+/// An IFD entry: tag, type, count and the value bytes.
+type Entry = (u16, u16, u32, Vec<u8>);
+
+/// A big-endian TIFF header with IFD0 and, when `exif` is not empty, an Exif
+/// sub-IFD. Entries must be sorted by tag; values longer than four bytes go
+/// into a data area behind the IFDs.
+fn tiff_with_entries(ifd0: &[Entry], exif: &[Entry]) -> Vec<u8> {
+    let ifd_size = |entries: usize| 2 + 12 * entries + 4;
+    let mut ifd0 = ifd0.to_vec();
+    let exif_offset = 8 + ifd_size(ifd0.len() + usize::from(!exif.is_empty()));
+    if !exif.is_empty() { ifd0.push((0x8769, 4, 1, (exif_offset as u32).to_be_bytes().to_vec())); }
+    let data_start = if exif.is_empty() { exif_offset } else { exif_offset + ifd_size(exif.len()) };
+
+    let mut tiff = vec![b'M', b'M', 0, 42, 0, 0, 0, 8];
+    let mut data = Vec::new();
+    for entries in [&ifd0[..], exif].into_iter().filter(|entries| !entries.is_empty()) {
+        tiff.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+        for (tag, kind, count, value) in entries {
+            tiff.extend_from_slice(&tag.to_be_bytes());
+            tiff.extend_from_slice(&kind.to_be_bytes());
+            tiff.extend_from_slice(&count.to_be_bytes());
+            if value.len() <= 4 {
+                tiff.extend(value.iter().copied().chain(std::iter::repeat(0)).take(4));
+            } else {
+                tiff.extend_from_slice(&((data_start + data.len()) as u32).to_be_bytes());
+                data.extend_from_slice(value);
+            }
+        }
+        tiff.extend_from_slice(&[0, 0, 0, 0]);
+    }
+    tiff.extend_from_slice(&data);
+    tiff
+}
+
+fn ascii(tag: u16, text: &str) -> Entry {
+    let mut value = text.as_bytes().to_vec();
+    value.push(0);
+    (tag, 2, value.len() as u32, value)
+}
+
+fn rational(tag: u16, numerator: u32, denominator: u32) -> Entry {
+    (tag, 5, 1, [numerator.to_be_bytes(), denominator.to_be_bytes()].concat())
+}
+
+fn write_jpeg_with_tiff(path: &Path, width: u32, height: u32, tiff: &[u8]) {
+    let mut jpeg = Vec::new();
+    image::RgbImage::new(width, height).write_to(&mut std::io::Cursor::new(&mut jpeg), image::ImageOutputFormat::Jpeg(90)).unwrap();
+
+    let mut app1 = b"Exif\0\0".to_vec();
+    app1.extend_from_slice(tiff);
+    let mut segment = vec![0xFF, 0xE1];
+    segment.extend_from_slice(&((app1.len() + 2) as u16).to_be_bytes());
+    segment.extend_from_slice(&app1);
+
+    let mut out = jpeg[..2].to_vec();
+    out.extend_from_slice(&segment);
+    out.extend_from_slice(&jpeg[2..]);
+    std::fs::write(path, out).unwrap();
+}
+
+#[test]
+fn exif_rotated_photo_is_classified_and_decoded_upright() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("phone.jpg");
+    // 传感器按横向存储，EXIF 6 = 顺时针旋转 90° 后显示为竖图
+    write_jpeg_with_orientation(&path, 40, 20, 6);
+    let folders = vec![dir.path().to_path_buf()];
+    let portrait = ScanFilter { orientation: ImageOrientationFilter::Portrait, ..ScanFilter::default() };
+
+    let mut index = ImageIndex::default();
+    assert_eq!(library::scan_image_files(&folders, &portrait, &mut index), vec![path.clone()]);
+    let entry = index.get(&path).unwrap();
+    assert_eq!((entry.exif_orientation, entry.display_size()), (6, (20, 40)));
+
+    let decoded = render::decode_image(&path, 0).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (20, 40));
+    let corrected = render::decode_image(&path, 3).unwrap();
+    assert_eq!((corrected.width(), corrected.height()), (40, 20));
+}
+
+#[test]
+fn manual_rotation_survives_reprobing() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("scan.jpg");
+    write_jpeg_with_orientation(&path, 40, 20, 1);
+    let folders = vec![dir.path().to_path_buf()];
+    let landscape = ScanFilter { orientation: ImageOrientationFilter::Landscape, ..ScanFilter::default() };
+
+    let mut index = ImageIndex::default();
+    library::scan_image_files(&folders, &landscape, &mut index);
+    index.set_user_rotation(&path, 1);
+    assert!(library::scan_image_files(&folders, &landscape, &mut index).is_empty());
+
+    // 文件被重新导出 (大小变化) 后仍保留手动旋转
+    write_jpeg_with_orientation(&path, 60, 20, 1);
+    assert!(library::scan_image_files(&folders, &landscape, &mut index).is_empty());
+    assert_eq!(index.get(&path).map(|e| (e.width, e.user_rotation)), Some((60, 1)));
+}
+
+#[test]
+fn downscaling_targets_the_upright_size() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("big.jpg");
+    write_jpeg_with_orientation(&path, 400, 200, 6);
+
+    // 竖着显示为 200x400，要覆盖 50x50 的区域只需 50x100
+    let options = DecodeOptions { target_size: Some(egui::vec2(50.0, 50.0)), ..DecodeOptions::default() };
+    let decoded = render::decode(&path, &options).unwrap();
+    assert_eq!((decoded.frames[0].size, decoded.downscaled_to), ([50, 100], Some(egui::vec2(50.0, 50.0))));
+
+    // 比目标还小的图片保持原始分辨率
+    let options = DecodeOptions { target_size: Some(egui::vec2(1000.0, 1000.0)), ..DecodeOptions::default() };
+    let decoded = render::decode(&path, &options).unwrap();
+    assert_eq!((decoded.frames[0].size, decoded.downscaled_to), ([200, 400], None));
+    assert_eq!(render::downscaled_size([4000, 3000], egui::vec2(400.0, 400.0)), Some([533, 400]));
+}
+
+#[test]
+fn blurred_background_is_small_and_upright() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("big.jpg");
+    write_jpeg_with_orientation(&path, 400, 200, 6);
+
+    assert!(render::decode(&path, &DecodeOptions::default()).unwrap().background.is_none());
+    let options = DecodeOptions { blurred_background: true, ..DecodeOptions::default() };
+    let background = render::decode(&path, &options).unwrap().background.unwrap();
+    assert_eq!(background.size, [32, 64]);
+}
+
+#[test]
+fn camera_lens_and_exposure_are_read_for_captions() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("camera.jpg");
+    let ifd0 = [ascii(0x010F, "Canon"), ascii(0x0110, "Canon EOS R5")];
+    let exif = [rational(0x829A, 1, 250), rational(0x829D, 28, 10), (0x8827, 3, 1, vec![0, 100]), rational(0x920A, 35, 1), ascii(0xA434, "RF24-70mm F2.8")];
+    write_jpeg_with_tiff(&path, 8, 8, &tiff_with_entries(&ifd0, &exif));
+
+    let data = ExifData::read(&path).unwrap();
+    assert_eq!(data.camera.as_deref(), Some("Canon EOS R5"));
+    assert_eq!(data.lens.as_deref(), Some("RF24-70mm F2.8"));
+    assert_eq!(data.exposure.as_deref(), Some("1/250 s · f/2.8 · ISO 100 · 35 mm"));
+
+    // 型号里没有厂商名时两者都显示；没有曝光信息时为空
+    write_jpeg_with_tiff(&path, 8, 8, &tiff_with_entries(&[ascii(0x010F, "FUJIFILM"), ascii(0x0110, "X-T4")], &[]));
+    let data = ExifData::read(&path).unwrap();
+    assert_eq!((data.camera.as_deref(), data.exposure), (Some("FUJIFILM X-T4"), None));
+}
+
+#[test]
+fn capture_date_orders_photos_and_undated_ones_come_last() {
+    let dir = tempfile::tempdir().unwrap();
+    let (newer, older, undated) = (dir.path().join("a.jpg"), dir.path().join("b.jpg"), dir.path().join("c.jpg"));
+    write_jpeg_with_date(&newer, "2021:06:15 10:30:00");
+    write_jpeg_with_date(&older, "2019:12:31 23:59:59");
+    write_jpeg_with_orientation(&undated, 8, 8, 1);
+
+    let mut index = ImageIndex::default();
+    let files = library::scan_image_files(&[dir.path().to_path_buf()], &ScanFilter::default(), &mut index);
+    let date = index.get(&newer).unwrap().capture_date;
+    assert_eq!(date, Some(CaptureDate { year: 2021, month: 6, day: 15, hour: 10, minute: 30, second: 0 }));
+
+    let mut playlist = Playlist::default();
+    playlist.set_order(PlaybackOrder::ByCaptureDate);
+    for file in files { playlist.insert_with_entry(file.clone(), index.get(&file)); }
+    let shown: Vec<_> = (0..4).map(|_| playlist.next_image().unwrap()).collect();
+    assert_eq!(shown, vec![older.clone(), newer, undated, older]);
+}
@@ -0,0 +1,62 @@
+use desktop_widget::{
+    focus,
+    index::{FileStamp, ImageIndex, IndexEntry},
+    library::{self, ScanFilter},
+};
+use image::{Rgb, RgbImage};
+
+/// A flat gray image with a black-and-white checkerboard patch around `center`.
+fn image_with_detail(width: u32, height: u32, center: (u32, u32)) -> RgbImage {
+    RgbImage::from_fn(width, height, |x, y| {
+        let in_patch = x.abs_diff(center.0) < 6 && y.abs_diff(center.1) < 6;
+        if in_patch { if (x + y) % 2 == 0 { Rgb([255, 255, 255]) } else { Rgb([0, 0, 0]) } } else { Rgb([128, 128, 128]) }
+    })
+}
+
+#[test]
+fn focus_point_is_where_the_detail_is() {
+    let (x, y) = focus::salient_point(&image_with_detail(90, 60, (15, 40))).unwrap();
+    assert!((x - 15.5 / 90.0).abs() < 0.03, "{x}");
+    assert!((y - 40.5 / 60.0).abs() < 0.03, "{y}");
+
+    assert_eq!(focus::salient_point(&RgbImage::from_pixel(90, 60, Rgb([10, 200, 10]))), None);
+}
+
+#[test]
+fn focus_is_detected_while_scanning_and_set_by_hand() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("portrait.png");
+    image_with_detail(60, 90, (30, 15)).save(&path).unwrap();
+    let folders = vec![dir.path().to_path_buf()];
+    let mut index = ImageIndex::default();
+
+    library::scan_image_files(&folders, &ScanFilter::default(), &mut index);
+    assert_eq!(index.get(&path).unwrap().focus, None);
+    library::scan_image_files(&folders, &ScanFilter { smart_crop: true, ..ScanFilter::default() }, &mut index);
+    let (_, y) = index.get(&path).and_then(IndexEntry::focus_point).unwrap();
+    assert!(y < 0.3, "{y}");
+
+    // 手动设置的焦点优先，并且在重新探测文件后保留
+    index.set_user_focus(&path, Some((0.5, 0.8)));
+    let stamp = FileStamp::read(&path).unwrap();
+    index.insert(path.clone(), IndexEntry::probe(&path, stamp).unwrap());
+    assert_eq!(index.get(&path).unwrap().focus_point(), Some((0.5, 0.8)));
+
+    // 旋转后坐标失效
+    index.set_user_rotation(&path, 1);
+    assert_eq!(index.get(&path).unwrap().focus_point(), None);
+}
+
+#[test]
+fn images_without_a_focus_point_are_analysed_once() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("flat.png");
+    RgbImage::from_pixel(90, 60, Rgb([10, 200, 10])).save(&path).unwrap();
+    let mut index = ImageIndex::default();
+
+    library::scan_image_files(&[dir.path().to_path_buf()], &ScanFilter { smart_crop: true, ..ScanFilter::default() }, &mut index);
+    let entry = index.get(&path).unwrap();
+    assert_eq!(entry.focus, None);
+    // 下次扫描看到这个标记就不再解码
+    assert!(entry.focus_detected);
+}
@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use desktop_widget::{
+    formats::{FormatFilter, FormatSettings},
+    index::ImageIndex,
+    library::{self, ScanFilter},
+};
+
+fn write_png(path: &Path) {
+    image::RgbImage::new(6, 3).save_with_format(path, image::ImageFormat::Png).unwrap();
+}
+
+#[test]
+fn default_formats_follow_enabled_codecs() {
+    let filter = FormatFilter::default();
+    for ext in ["jpg", "jpeg", "png", "gif", "bmp", "webp", "tif", "tiff", "ico"] {
+        assert!(filter.extensions().contains(&ext), "{ext} should be supported");
+    }
+    assert!(filter.matches(Path::new("holiday.WEBP")));
+    assert!(!filter.matches(Path::new("notes.txt")));
+}
+
+#[test]
+fn include_and_exclude_override_the_defaults() {
+    let settings = FormatSettings {
+        include_extensions: vec![".JFIF".into()],
+        exclude_extensions: vec!["ico".into()],
+        sniff_content: false,
+    };
+    let filter = FormatFilter::new(&settings);
+    assert!(filter.matches(Path::new("scan.jfif")));
+    assert!(!filter.matches(Path::new("favicon.ico")));
+}
+
+#[test]
+fn sniffing_finds_images_without_a_usable_extension() {
+    let dir = tempfile::tempdir().unwrap();
+    write_png(&dir.path().join("no_extension"));
+    write_png(&dir.path().join("export.dat"));
+    // 扩展名错误的文件按内容探测尺寸
+    write_png(&dir.path().join("mislabelled.jpg"));
+    std::fs::write(dir.path().join("readme.dat"), "plain text").unwrap();
+    let folders = vec![dir.path().to_path_buf()];
+
+    let mut index = ImageIndex::default();
+    let found = library::scan_image_files(&folders, &ScanFilter::default(), &mut index);
+    assert_eq!(found, vec![dir.path().join("mislabelled.jpg")]);
+
+    let sniffing = ScanFilter { formats: FormatFilter::new(&FormatSettings { sniff_content: true, ..Default::default() }), ..Default::default() };
+    let mut found = library::scan_image_files(&folders, &sniffing, &mut index);
+    found.sort();
+    let expected: Vec<_> = ["export.dat", "mislabelled.jpg", "no_extension"].iter().map(|f| dir.path().join(f)).collect();
+    assert_eq!(found, expected);
+}
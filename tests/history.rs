@@ -0,0 +1,55 @@
+use std::path::{Path, PathBuf};
+
+use desktop_widget::history::History;
+
+fn push_all(history: &mut History, names: &[&str], max_len: usize) {
+    for name in names { history.push(PathBuf::from(name), max_len); }
+}
+
+#[test]
+fn back_and_forward_walk_the_shown_photos() {
+    let mut history = History::default();
+    push_all(&mut history, &["a.jpg", "b.jpg", "c.jpg"], 10);
+
+    assert_eq!(history.back(), Some("b.jpg".into()));
+    assert_eq!(history.back(), Some("a.jpg".into()));
+    assert_eq!(history.back(), None);
+    assert_eq!(history.forward(), Some("b.jpg".into()));
+
+    // 后退之后显示新图片会丢弃前进方向的条目
+    history.push("d.jpg".into(), 10);
+    assert_eq!(history.forward(), None);
+    assert_eq!(history.back(), Some("b.jpg".into()));
+    assert_eq!(history.len(), 3);
+}
+
+#[test]
+fn oldest_entries_are_dropped_beyond_the_limit() {
+    let mut history = History::default();
+    push_all(&mut history, &["1.jpg", "2.jpg", "3.jpg", "4.jpg"], 3);
+    assert_eq!(history.len(), 3);
+    assert_eq!(history.current(), Some(Path::new("4.jpg")));
+
+    history.set_max_len(2);
+    assert_eq!(history.back(), Some("3.jpg".into()));
+    assert_eq!(history.back(), None);
+}
+
+#[test]
+fn history_survives_a_restart_without_deleted_photos() {
+    let dir = tempfile::tempdir().unwrap();
+    let photos: Vec<PathBuf> = ["a.png", "b.png", "c.png"].iter().map(|name| dir.path().join(name)).collect();
+    for photo in &photos { std::fs::write(photo, b"").unwrap(); }
+
+    let mut history = History::default();
+    for photo in &photos { history.push(photo.clone(), 10); }
+    history.back();
+    let file = dir.path().join("history.json");
+    history.save(&file);
+    std::fs::remove_file(&photos[0]).unwrap();
+
+    let mut restored = History::load(&file);
+    assert_eq!(restored.current(), Some(photos[1].as_path()));
+    assert_eq!(restored.back(), None);
+    assert_eq!(restored.forward(), Some(photos[2].clone()));
+}
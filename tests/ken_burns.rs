@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use desktop_widget::ken_burns::{KenBurnsPath, KenBurnsSettings, IDLE_PASS};
+use egui::{pos2, vec2, Rect};
+use rand::{rngs::StdRng, SeedableRng};
+
+const LANDSCAPE: egui::Vec2 = vec2(4000.0, 3000.0);
+const WINDOW: egui::Vec2 = vec2(300.0, 400.0);
+
+fn inside_image(uv: Rect) -> bool {
+    Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)).expand(1e-5).contains_rect(uv)
+}
+
+#[test]
+fn moves_go_back_and_forth_over_the_display_time() {
+    let settings = KenBurnsSettings { enabled: true, speed: 2.0, ..KenBurnsSettings::default() };
+    let minute = Duration::from_secs(60);
+    assert_eq!(settings.progress(Duration::ZERO, minute), 0.0);
+    assert_eq!(settings.progress(Duration::from_secs(15), minute), 0.5);
+    assert_eq!(settings.progress(Duration::from_secs(30), minute), 1.0);
+    assert_eq!(settings.progress(Duration::from_secs(45), minute), 0.5);
+    // 不自动切换时按固定时长移动
+    assert_eq!(settings.progress(IDLE_PASS / 4, Duration::ZERO), 0.5);
+}
+
+#[test]
+fn zoom_in_ends_on_a_smaller_centered_crop() {
+    let settings = KenBurnsSettings { enabled: true, path: KenBurnsPath::ZoomIn, max_zoom: 1.5, ..KenBurnsSettings::default() };
+    let motion = settings.new_move(&mut StdRng::seed_from_u64(1));
+    let start = motion.uv(LANDSCAPE, WINDOW, 0.0);
+    let end = motion.uv(LANDSCAPE, WINDOW, 1.0);
+
+    assert_eq!(start.height(), 1.0);
+    assert!((start.width() / end.width() - 1.5).abs() < 1e-4);
+    assert!((end.center() - pos2(0.5, 0.5)).length() < 1e-4);
+    // 裁剪区域始终保持窗口的宽高比
+    assert!((end.width() * LANDSCAPE.x / (end.height() * LANDSCAPE.y) - WINDOW.x / WINDOW.y).abs() < 1e-3);
+}
+
+#[test]
+fn random_moves_stay_inside_the_image() {
+    let settings = KenBurnsSettings { enabled: true, max_zoom: 2.0, ..KenBurnsSettings::default() };
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..50 {
+        let motion = settings.new_move(&mut rng);
+        assert!(motion.from.zoom != motion.to.zoom);
+        for step in 0..=10 {
+            let progress = step as f32 / 10.0;
+            assert!(inside_image(motion.uv(LANDSCAPE, WINDOW, progress)));
+            assert!(inside_image(motion.uv(vec2(3000.0, 4000.0), vec2(400.0, 300.0), progress)));
+        }
+    }
+}
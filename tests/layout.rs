@@ -0,0 +1,80 @@
+use desktop_widget::{
+    config::{AppConfig, FitMode, ResizeAnchor},
+    layout,
+};
+use egui::{pos2, vec2, Rect};
+
+#[test]
+fn cover_uses_orientation_preset() {
+    let config = AppConfig::default();
+    assert_eq!(layout::window_size(&config, Some(vec2(4000.0, 3000.0))), vec2(400.0, 300.0));
+    assert_eq!(layout::window_size(&config, Some(vec2(3000.0, 4000.0))), vec2(300.0, 400.0));
+    assert_eq!(layout::window_size(&config, None), vec2(400.0, 300.0));
+
+    let letterbox = AppConfig { fit_mode: FitMode::Letterbox, ..AppConfig::default() };
+    assert_eq!(layout::window_size(&letterbox, Some(vec2(1000.0, 3000.0))), vec2(300.0, 400.0));
+}
+
+#[test]
+fn contain_matches_image_aspect() {
+    let config = AppConfig { fit_mode: FitMode::Contain, ..AppConfig::default() };
+    // 更宽的图片：保持预设高度，拉长宽度
+    assert_eq!(layout::window_size(&config, Some(vec2(2000.0, 1000.0))), vec2(600.0, 300.0));
+    // 更高的图片：保持预设宽度
+    assert_eq!(layout::window_size(&config, Some(vec2(1000.0, 1000.0))), vec2(400.0, 400.0));
+}
+
+#[test]
+fn display_pixels_scale_with_the_display() {
+    let config = AppConfig::default();
+    assert_eq!(layout::display_pixels(&config, vec2(4000.0, 3000.0), 2.0), vec2(800.0, 600.0));
+}
+
+#[test]
+fn anchor_keeps_corner_fixed() {
+    let pos = pos2(100.0, 100.0);
+    let (old, new) = (vec2(400.0, 300.0), vec2(300.0, 400.0));
+    assert_eq!(layout::anchored_position(ResizeAnchor::TopLeft, pos, old, new), pos);
+    assert_eq!(layout::anchored_position(ResizeAnchor::BottomRight, pos, old, new), pos2(200.0, 0.0));
+    assert_eq!(layout::anchored_position(ResizeAnchor::Center, pos, old, new), pos2(150.0, 50.0));
+}
+
+#[test]
+fn clamp_moves_window_back_on_screen() {
+    let screen = vec2(1920.0, 1080.0);
+    assert_eq!(layout::clamp_to_screen(pos2(10.0, 10.0), vec2(400.0, 300.0), screen), None);
+    assert_eq!(layout::clamp_to_screen(pos2(-5.0, 900.0), vec2(400.0, 300.0), screen), Some(pos2(0.0, 780.0)));
+}
+
+#[test]
+fn cover_uv_crops_centered() {
+    let uv = layout::cover_uv(vec2(2000.0, 1000.0), vec2(100.0, 100.0));
+    assert!((uv.min.x - 0.25).abs() < 1e-6 && (uv.max.x - 0.75).abs() < 1e-6);
+    assert_eq!((uv.min.y, uv.max.y), (0.0, 1.0));
+}
+
+#[test]
+fn focused_cover_uv_follows_the_focus_inside_the_image() {
+    let texture = vec2(2000.0, 1000.0);
+    let uv = layout::focused_cover_uv(texture, vec2(100.0, 100.0), pos2(0.3, 0.9));
+    assert!((uv.min.x - 0.05).abs() < 1e-6 && (uv.max.x - 0.55).abs() < 1e-6);
+    assert_eq!((uv.min.y, uv.max.y), (0.0, 1.0));
+    // 焦点太靠边时裁剪区域停在图片边缘
+    let uv = layout::focused_cover_uv(texture, vec2(100.0, 100.0), pos2(0.95, 0.5));
+    assert_eq!((uv.min.x, uv.max.x), (0.5, 1.0));
+}
+
+#[test]
+fn contain_placement_letterboxes_other_aspect_ratios() {
+    let window = Rect::from_min_size(pos2(0.0, 0.0), vec2(400.0, 300.0));
+    let (rect, uv) = layout::image_placement(FitMode::Contain, vec2(300.0, 400.0), window);
+    assert_eq!(rect, Rect::from_min_max(pos2(87.5, 0.0), pos2(312.5, 300.0)));
+    assert_eq!(uv, Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)));
+
+    let (letterboxed, _) = layout::image_placement(FitMode::Letterbox, vec2(300.0, 400.0), window);
+    assert_eq!(letterboxed, rect);
+
+    let (rect, uv) = layout::image_placement(FitMode::Cover, vec2(300.0, 400.0), window);
+    assert_eq!(rect, window);
+    assert!(uv.height() < 1.0 && uv.width() == 1.0);
+}
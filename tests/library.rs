@@ -0,0 +1,270 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use desktop_widget::{
+    config::{FolderEntry, ImageOrientationFilter, PlaybackOrder},
+    index::ImageIndex,
+    library::{self, FolderRules, ImageLimits, ScanFilter},
+    playlist::{Playlist, SortKey},
+    scanner::Scanner,
+};
+
+fn write_png(path: &Path, width: u32, height: u32) {
+    image::RgbImage::new(width, height).save(path).unwrap();
+}
+
+fn orientation(orientation: ImageOrientationFilter) -> ScanFilter {
+    ScanFilter { orientation, ..ScanFilter::default() }
+}
+
+#[test]
+fn scan_filters_by_extension_and_orientation() {
+    let dir = tempfile::tempdir().unwrap();
+    write_png(&dir.path().join("wide.png"), 40, 20);
+    std::fs::create_dir(dir.path().join("nested")).unwrap();
+    write_png(&dir.path().join("nested/tall.png"), 20, 40);
+    std::fs::write(dir.path().join("notes.txt"), "not an image").unwrap();
+    let folders = vec![dir.path().to_path_buf()];
+    let mut index = ImageIndex::default();
+
+    assert_eq!(library::scan_image_files(&folders, &ScanFilter::default(), &mut index).len(), 2);
+    let landscape = library::scan_image_files(&folders, &orientation(ImageOrientationFilter::Landscape), &mut index);
+    assert_eq!(landscape, vec![dir.path().join("wide.png")]);
+    let portrait = library::scan_image_files(&folders, &orientation(ImageOrientationFilter::Portrait), &mut index);
+    assert_eq!(portrait, vec![dir.path().join("nested/tall.png")]);
+}
+
+#[test]
+fn squares_and_size_limits_are_filtered_while_indexing() {
+    let dir = tempfile::tempdir().unwrap();
+    write_png(&dir.path().join("square.png"), 100, 101);
+    write_png(&dir.path().join("wide.png"), 300, 100);
+    write_png(&dir.path().join("icon.png"), 16, 16);
+    let folders = vec![dir.path().to_path_buf()];
+    let mut index = ImageIndex::default();
+    let mut scan = |filter: ScanFilter| {
+        let mut found = library::scan_image_files(&folders, &filter, &mut index);
+        found.sort();
+        found.iter().map(|path| path.file_name().unwrap().to_string_lossy().into_owned()).collect::<Vec<_>>()
+    };
+
+    // 接近正方形的图片既不算横向也不算纵向
+    assert_eq!(scan(orientation(ImageOrientationFilter::Square)), ["icon.png", "square.png"]);
+    assert_eq!(scan(orientation(ImageOrientationFilter::Landscape)), ["wide.png"]);
+    let strict = ScanFilter { limits: ImageLimits { square_tolerance: 0.0, ..ImageLimits::default() }, ..orientation(ImageOrientationFilter::Portrait) };
+    assert_eq!(scan(strict), ["square.png"]);
+
+    let limits = |limits: ImageLimits| ScanFilter { limits, ..ScanFilter::default() };
+    assert_eq!(scan(limits(ImageLimits { min_width: 50, min_height: 50, ..ImageLimits::default() })), ["square.png", "wide.png"]);
+    assert_eq!(scan(limits(ImageLimits { max_aspect_ratio: Some(2.0), ..ImageLimits::default() })), ["icon.png", "square.png"]);
+    assert_eq!(scan(limits(ImageLimits { min_file_size_kb: Some(1024), ..ImageLimits::default() })), Vec::<String>::new());
+    assert_eq!(scan(limits(ImageLimits { max_file_size_mb: Some(1), ..ImageLimits::default() })).len(), 3);
+}
+
+#[test]
+fn folder_rules_limit_what_is_scanned() {
+    let dir = tempfile::tempdir().unwrap();
+    for sub in ["@eaDir", ".cache", "a/b", "raw"] { std::fs::create_dir_all(dir.path().join(sub)).unwrap(); }
+    for file in ["top.png", "skip.png", "@eaDir/thumb.png", ".cache/c.png", ".dot.png", "a/one.png", "a/b/two.png", "raw/r.png"] {
+        write_png(&dir.path().join(file), 4, 4);
+    }
+    let scan = |folder: FolderEntry| {
+        let filter = ScanFilter { folders: Arc::new(vec![FolderRules::new(&folder)]), ..ScanFilter::default() };
+        let mut found = library::scan_image_files(std::slice::from_ref(&folder.path), &filter, &mut ImageIndex::default());
+        found.sort();
+        found.iter().map(|path| path.strip_prefix(&folder.path).unwrap().to_string_lossy().replace('\\', "/")).collect::<Vec<_>>()
+    };
+    let folder = FolderEntry::new(dir.path().to_path_buf());
+
+    // 默认跳过隐藏文件和缩略图目录
+    assert_eq!(scan(folder.clone()), ["a/b/two.png", "a/one.png", "raw/r.png", "skip.png", "top.png"]);
+    assert_eq!(scan(FolderEntry { max_depth: Some(1), exclude: vec!["@eaDir".into(), "skip.*".into(), "raw/**".into()], ..folder.clone() }), ["a/one.png", "top.png"]);
+    assert_eq!(scan(FolderEntry { include: vec!["t*.png".into()], include_hidden: true, exclude: Vec::new(), ..folder.clone() }), ["@eaDir/thumb.png", "a/b/two.png", "top.png"]);
+    assert_eq!(scan(FolderEntry { max_depth: Some(0), include_hidden: true, ..folder }), [".dot.png", "skip.png", "top.png"]);
+
+    // 监视器报告的单个文件按同样的规则检查
+    let filter = ScanFilter { folders: Arc::new(vec![FolderRules::new(&FolderEntry::new(dir.path().to_path_buf()))]), ..ScanFilter::default() };
+    assert!(filter.in_scope(&dir.path().join("a/b/two.png")));
+    assert!(!filter.in_scope(&dir.path().join(".cache/c.png")));
+    assert!(!filter.in_scope(&dir.path().join("@eaDir/thumb.png")));
+    assert!(library::pattern_error("[a-").is_some());
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinked_files_are_only_scanned_when_following_symlinks() {
+    let dir = tempfile::tempdir().unwrap();
+    let (library, elsewhere) = (dir.path().join("library"), dir.path().join("elsewhere"));
+    std::fs::create_dir(&library).unwrap();
+    std::fs::create_dir(&elsewhere).unwrap();
+    write_png(&library.join("own.png"), 4, 4);
+    write_png(&elsewhere.join("target.png"), 4, 4);
+    let link = library.join("link.png");
+    std::os::unix::fs::symlink(elsewhere.join("target.png"), &link).unwrap();
+
+    let scan = |follow_symlinks: bool| {
+        let folder = FolderEntry { follow_symlinks, ..FolderEntry::new(library.clone()) };
+        let filter = ScanFilter { folders: Arc::new(vec![FolderRules::new(&folder)]), ..ScanFilter::default() };
+        let mut found = library::scan_image_files(std::slice::from_ref(&library), &filter, &mut ImageIndex::default());
+        found.sort();
+        (found, filter.is_candidate(&link))
+    };
+    assert_eq!(scan(false), (vec![library.join("own.png")], false));
+    assert_eq!(scan(true), (vec![library.join("link.png"), library.join("own.png")], true));
+}
+
+#[test]
+fn index_is_reused_until_a_file_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let image_path = dir.path().join("photo.png");
+    write_png(&image_path, 40, 20);
+    let folders = vec![dir.path().to_path_buf()];
+    let index_path = dir.path().join("index.json");
+
+    let mut index = ImageIndex::default();
+    library::scan_image_files(&folders, &ScanFilter::default(), &mut index);
+    index.save(&index_path);
+    assert!(!index.is_dirty());
+
+    // 重新加载后再次扫描：文件没有变化，索引也不应被标记为已修改
+    let mut index = ImageIndex::load(&index_path);
+    assert_eq!(index.len(), 1);
+    library::scan_image_files(&folders, &ScanFilter::default(), &mut index);
+    assert!(!index.is_dirty());
+
+    // 文件内容 (大小) 变化后会重新探测尺寸
+    write_png(&image_path, 20, 60);
+    let portrait = library::scan_image_files(&folders, &orientation(ImageOrientationFilter::Portrait), &mut index);
+    assert_eq!(portrait, vec![image_path.clone()]);
+    assert_eq!(index.get(&image_path).map(|e| (e.width, e.height)), Some((20, 60)));
+
+    // 删除的文件会从索引中清除
+    std::fs::remove_file(&image_path).unwrap();
+    library::scan_image_files(&folders, &ScanFilter::default(), &mut index);
+    assert!(index.is_empty());
+}
+
+#[test]
+fn playlist_shows_every_image_before_repeating() {
+    let files: Vec<_> = (0..10).map(|i| format!("{i}.jpg").into()).collect();
+    let mut playlist = Playlist::new(files.clone());
+
+    let mut shown: Vec<_> = (0..10).map(|_| playlist.next_image().unwrap()).collect();
+    shown.sort();
+    let mut expected = files;
+    expected.sort();
+    assert_eq!(shown, expected);
+    assert!(playlist.next_image().is_some());
+    assert!(Playlist::default().next_image().is_none());
+}
+
+#[test]
+fn sequential_orders_wrap_around_and_resume() {
+    let files = ["b/2.jpg", "a/3.jpg", "b/1.jpg", "a b/0.jpg"];
+    let mut playlist = Playlist::default();
+    playlist.set_order(PlaybackOrder::ByName);
+    for file in files { playlist.insert(file.into()); }
+    let shown: Vec<_> = (0..5).map(|_| playlist.next_image().unwrap()).collect();
+    assert_eq!(shown, ["a b/0.jpg", "b/1.jpg", "b/2.jpg", "a/3.jpg", "a b/0.jpg"].map(PathBuf::from));
+
+    // 按文件夹：同一目录的图片连在一起
+    playlist.set_order(PlaybackOrder::ByFolder);
+    for file in files { playlist.insert(file.into()); }
+    assert_eq!(playlist.upcoming(4), ["a/3.jpg", "a b/0.jpg", "b/1.jpg", "b/2.jpg"].map(PathBuf::from));
+
+    // 重启后从上次显示的图片之后继续，即使列表还是空的
+    playlist.set_order(PlaybackOrder::ByFolder);
+    playlist.resume_after(SortKey::new(PlaybackOrder::ByFolder, Path::new("b/1.jpg"), None));
+    for file in files { playlist.insert(file.into()); }
+    assert_eq!(playlist.next_image(), Some("b/2.jpg".into()));
+    assert_eq!(playlist.next_image(), Some("a/3.jpg".into()));
+}
+
+#[test]
+fn folders_are_sampled_by_weight_not_by_size() {
+    let mut playlist = Playlist::default();
+    playlist.set_folders(&[("/big".into(), 1.0), ("/small".into(), 1.0), ("/big/rare".into(), 0.0)]);
+    for i in 0..1000 { playlist.insert(format!("/big/{i}.jpg").into()); }
+    for i in 0..10 { playlist.insert(format!("/small/{i}.jpg").into()); }
+    // 嵌套的文件夹有自己的权重；权重为 0 时从不抽到
+    playlist.insert("/big/rare/0.jpg".into());
+
+    let shown: Vec<_> = (0..2000).map(|_| playlist.next_image().unwrap()).collect();
+    let small = shown.iter().filter(|path| path.starts_with("/small")).count();
+    assert!((800..1200).contains(&small), "{small} of 2000 from the small folder");
+    assert!(!shown.contains(&PathBuf::from("/big/rare/0.jpg")));
+    // 小文件夹内部仍然是一轮不重复
+    let first_round: HashSet<_> = shown.iter().filter(|path| path.starts_with("/small")).take(10).collect();
+    assert_eq!(first_round.len(), 10);
+}
+
+#[test]
+fn rated_images_are_picked_more_often() {
+    let mut playlist = Playlist::default();
+    playlist.set_order(PlaybackOrder::Random);
+    for i in 0..10 { playlist.insert(format!("{i}.jpg").into()); }
+    playlist.set_image_weight("0.jpg".into(), 10.0);
+    playlist.set_image_weight("1.jpg".into(), 0.0);
+
+    let shown: Vec<_> = (0..1900).map(|_| playlist.next_image().unwrap()).collect();
+    let favorite = shown.iter().filter(|path| *path == Path::new("0.jpg")).count();
+    // 权重 10 对其余 8 张各 1：约占一半
+    assert!((800..1100).contains(&favorite), "{favorite} of 1900");
+    assert!(!shown.contains(&PathBuf::from("1.jpg")));
+}
+
+#[test]
+fn hidden_images_are_skipped_but_stay_indexed() {
+    let dir = tempfile::tempdir().unwrap();
+    write_png(&dir.path().join("shown.png"), 40, 20);
+    write_png(&dir.path().join("hidden.png"), 40, 20);
+    let folders = vec![dir.path().to_path_buf()];
+    let filter = ScanFilter { hidden: Arc::new(HashSet::from([dir.path().join("hidden.png")])), ..ScanFilter::default() };
+    let mut index = ImageIndex::default();
+    library::scan_image_files(&folders, &ScanFilter::default(), &mut index);
+
+    assert_eq!(library::scan_image_files(&folders, &filter, &mut index), vec![dir.path().join("shown.png")]);
+    // 隐藏不会把条目从索引中清除
+    assert_eq!(index.len(), 2);
+}
+
+#[test]
+fn upcoming_matches_what_next_image_returns() {
+    let files: Vec<_> = (0..5).map(|i| format!("{i}.jpg").into()).collect();
+    let mut playlist = Playlist::new(files);
+
+    let upcoming = playlist.upcoming(3).to_vec();
+    assert_eq!(upcoming.len(), 3);
+    assert_eq!((0..3).map(|_| playlist.next_image().unwrap()).collect::<Vec<_>>(), upcoming);
+    // 本轮只剩两张
+    assert_eq!(playlist.upcoming(3).len(), 2);
+}
+
+#[test]
+fn background_scanner_streams_images_and_reports_progress() {
+    let dir = tempfile::tempdir().unwrap();
+    for i in 0..5 {
+        write_png(&dir.path().join(format!("{i}.png")), 10, 10);
+    }
+    std::fs::write(dir.path().join("broken.jpg"), "not really a jpeg").unwrap();
+    let index = Arc::new(Mutex::new(ImageIndex::default()));
+    let mut scanner = Scanner::start(vec![dir.path().to_path_buf()], ScanFilter::default(), index.clone());
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut found = Vec::new();
+    while !scanner.is_finished() {
+        assert!(Instant::now() < deadline, "scan did not finish");
+        found.extend(scanner.poll());
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    assert_eq!(found.len(), 5);
+    let progress = scanner.progress();
+    assert_eq!((progress.files_seen, progress.images_accepted, progress.errors), (6, 5, 1));
+    assert!(progress.finished);
+    assert_eq!(index.lock().unwrap().len(), 5);
+}
@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use desktop_widget::{
+    exif_data::CaptureDate,
+    index::{FileStamp, IndexEntry},
+    memories::{DateFilter, DateSelection, Day, MemorySettings},
+    playlist::Playlist,
+};
+
+/// An index entry for a photo taken on `date`, or only modified at `mtime` (ms).
+fn entry(date: Option<(u16, u8, u8)>, mtime: u64) -> IndexEntry {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("photo.png");
+    image::RgbImage::new(4, 4).save(&path).unwrap();
+    let mut entry = IndexEntry::probe(&path, FileStamp::read(&path).unwrap()).unwrap();
+    entry.mtime = mtime;
+    entry.capture_date = date.map(|(year, month, day)| CaptureDate { year, month, day, hour: 12, minute: 0, second: 0 });
+    entry
+}
+
+fn settings(selection: DateSelection) -> MemorySettings {
+    MemorySettings { selection, ..MemorySettings::default() }
+}
+
+#[test]
+fn unix_times_become_local_calendar_days() {
+    assert_eq!(Day::from_unix_seconds(0, 0), Day::new(1970, 1, 1));
+    assert_eq!(Day::from_unix_seconds(951_782_400, 0), Day::new(2000, 2, 29));
+    // 2024-12-31 20:00 UTC 在 UTC+8 已经是新年
+    assert_eq!(Day::from_unix_seconds(1_735_675_200, 0), Day::new(2024, 12, 31));
+    assert_eq!(Day::from_unix_seconds(1_735_675_200, 8 * 3600), Day::new(2025, 1, 1));
+    assert_eq!(Day::from_unix_seconds(-1, 0), Day::new(1969, 12, 31));
+}
+
+#[test]
+fn on_this_day_matches_earlier_years_around_the_date() {
+    let today = Day::new(2025, 1, 2);
+    let exact = DateFilter::at(settings(DateSelection::OnThisDay), today, 0).unwrap();
+    assert!(exact.matches(&entry(Some((2019, 1, 2)), 0)));
+    assert!(!exact.matches(&entry(Some((2019, 1, 3)), 0)));
+    // 今年的照片不算回忆
+    assert!(!exact.matches(&entry(Some((2025, 1, 2)), 0)));
+
+    let around = DateFilter::at(MemorySettings { days_around: 3, ..settings(DateSelection::OnThisDay) }, today, 0).unwrap();
+    assert!(around.matches(&entry(Some((2018, 12, 30)), 0)));
+    assert!(!around.matches(&entry(Some((2018, 12, 20)), 0)));
+    // 没有拍摄日期时使用文件的修改日期 (2020-01-02 12:00 UTC)
+    assert!(around.matches(&entry(None, 1_577_966_400_000)));
+
+    assert!(DateFilter::at(settings(DateSelection::All), today, 0).is_none());
+}
+
+#[test]
+fn date_range_falls_back_to_all_photos_when_nothing_matches() {
+    let range = MemorySettings { from: Day::new(2010, 6, 1), to: Day::new(2010, 8, 31), ..settings(DateSelection::Range) };
+    let filter = DateFilter::at(range, Day::new(2025, 1, 1), 0);
+    let summer = entry(Some((2010, 7, 14)), 0);
+    let winter = entry(Some((2010, 12, 24)), 0);
+
+    let mut playlist = Playlist::default();
+    playlist.set_date_filter(filter);
+    playlist.insert_with_entry("winter.jpg".into(), Some(&winter));
+    assert_eq!(playlist.matching_len(), 0);
+    assert_eq!(playlist.next_image(), Some("winter.jpg".into()));
+
+    playlist.insert_with_entry("summer.jpg".into(), Some(&summer));
+    assert_eq!((playlist.matching_len(), playlist.len()), (1, 2));
+    assert!((0..5).all(|_| playlist.next_image().as_deref() == Some(Path::new("summer.jpg"))));
+
+    playlist.remove(Path::new("summer.jpg"));
+    assert_eq!(playlist.next_image(), Some("winter.jpg".into()));
+}
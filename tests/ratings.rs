@@ -0,0 +1,36 @@
+use std::path::Path;
+
+use desktop_widget::ratings::{ImageRating, Ratings, MAX_STARS};
+
+#[test]
+fn stars_and_favorites_raise_the_playback_weight() {
+    assert_eq!(ImageRating::default().playback_weight(), 1.0);
+    assert_eq!(ImageRating { stars: 3, ..ImageRating::default() }.playback_weight(), 4.0);
+    assert_eq!(ImageRating { favorite: true, stars: 1, hidden: false }.playback_weight(), 4.0);
+    assert_eq!(ImageRating { stars: 9, ..ImageRating::default() }.playback_weight(), 1.0 + f64::from(MAX_STARS));
+}
+
+#[test]
+fn ratings_survive_a_restart_and_follow_renames() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("ratings.json");
+    let mut ratings = Ratings::default();
+    ratings.update(Path::new("/photos/a.jpg"), |rating| rating.stars = 9);
+    ratings.update(Path::new("/photos/b.jpg"), |rating| rating.hidden = true);
+    ratings.update(Path::new("/photos/c.jpg"), |rating| rating.favorite = true);
+    // 恢复默认值的条目被删除
+    ratings.update(Path::new("/photos/c.jpg"), |rating| rating.favorite = false);
+    ratings.save(&file);
+
+    let mut ratings = Ratings::load(&file);
+    assert_eq!(ratings.get(Path::new("/photos/a.jpg")).stars, MAX_STARS);
+    assert!(ratings.is_hidden(Path::new("/photos/b.jpg")));
+    assert_eq!(ratings.get(Path::new("/photos/c.jpg")).playback_weight(), 1.0);
+
+    ratings.rename(Path::new("/photos"), Path::new("/archive"));
+    assert_eq!(ratings.get(Path::new("/archive/a.jpg")).stars, MAX_STARS);
+    assert_eq!(ratings.get(Path::new("/photos/a.jpg")), ImageRating::default());
+
+    ratings.unhide_all();
+    assert!(ratings.hidden().is_empty());
+}
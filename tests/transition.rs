@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use desktop_widget::{
+    config::FitMode,
+    transition::{Layer, SlideDirection, TransitionKind, TransitionSettings, DISSOLVE_GRID},
+};
+use egui::{pos2, vec2, Rect};
+
+fn window() -> Rect { Rect::from_min_size(pos2(0.0, 0.0), vec2(400.0, 300.0)) }
+
+fn layers(settings: TransitionSettings, progress: f32) -> (Vec<Layer>, Vec<Layer>) {
+    let outgoing = Layer::placed(FitMode::Cover, vec2(4000.0, 3000.0), window());
+    let incoming = Layer::placed(FitMode::Cover, vec2(3000.0, 4000.0), window());
+    settings.layers(progress, window(), outgoing, incoming)
+}
+
+#[test]
+fn progress_is_eased_and_clamped() {
+    let settings = TransitionSettings { duration_ms: 1000, ..TransitionSettings::default() };
+    assert_eq!(settings.progress(Duration::ZERO), 0.0);
+    assert_eq!(settings.progress(Duration::from_millis(500)), 0.5);
+    assert!(settings.progress(Duration::from_millis(100)) < 0.1);
+    assert_eq!(settings.progress(Duration::from_secs(5)), 1.0);
+    assert!(!TransitionSettings { kind: TransitionKind::None, ..settings }.is_enabled());
+}
+
+#[test]
+fn crossfade_fades_the_new_image_in_over_the_old_one() {
+    let (outgoing, incoming) = layers(TransitionSettings::default(), 0.25);
+    assert_eq!(outgoing[0].opacity, 1.0);
+    assert_eq!(incoming[0].opacity, 0.25);
+    // 两张图片都按各自的宽高比裁剪
+    assert!(outgoing[0].uv.width() == 1.0 && incoming[0].uv.height() < 1.0);
+}
+
+#[test]
+fn slide_pushes_the_old_image_out() {
+    let settings = TransitionSettings { kind: TransitionKind::Slide, direction: SlideDirection::Right, ..TransitionSettings::default() };
+    let (outgoing, incoming) = layers(settings, 0.25);
+    assert_eq!(outgoing[0].rect.min, pos2(-100.0, 0.0));
+    assert_eq!(incoming[0].rect.min, pos2(300.0, 0.0));
+
+    let settings = TransitionSettings { direction: SlideDirection::Top, ..settings };
+    let (_, incoming) = layers(settings, 1.0);
+    assert_eq!(incoming[0].rect, window());
+}
+
+#[test]
+fn zoom_fade_keeps_the_old_image_opaque() {
+    let settings = TransitionSettings { kind: TransitionKind::ZoomFade, ..TransitionSettings::default() };
+    let (outgoing, incoming) = layers(settings, 0.5);
+    assert_eq!(outgoing[0].opacity, 1.0);
+    assert!(outgoing[0].rect.width() > window().width());
+    assert_eq!(incoming[0].opacity, 0.5);
+}
+
+#[test]
+fn dissolve_reveals_every_cell_by_the_end() {
+    let settings = TransitionSettings { kind: TransitionKind::Dissolve, ..TransitionSettings::default() };
+    assert!(layers(settings, 0.0).1.is_empty());
+    let (_, halfway) = layers(settings, 0.5);
+    assert!(!halfway.is_empty() && halfway.len() < DISSOLVE_GRID * DISSOLVE_GRID);
+
+    let (_, done) = layers(settings, 1.0);
+    assert_eq!(done.len(), DISSOLVE_GRID * DISSOLVE_GRID);
+    assert!(done.iter().all(|cell| cell.opacity == 1.0 && window().contains_rect(cell.rect)));
+    let area: f32 = done.iter().map(|cell| cell.rect.area()).sum();
+    assert!((area - window().area()).abs() < 1.0);
+}
@@ -0,0 +1,106 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use desktop_widget::{
+    config::WatchMode,
+    index::ImageIndex,
+    library::ScanFilter,
+    playlist::Playlist,
+    watcher::{FolderWatcher, LibraryChange, RENAME_TIMEOUT},
+};
+
+fn wait_for(watcher: &FolderWatcher, expected: &LibraryChange) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if watcher.poll().contains(expected) {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    panic!("watcher did not report {expected:?}");
+}
+
+fn write_png(path: &Path) {
+    // 先写到临时文件再改名，避免监视器看到写了一半的文件
+    let tmp = path.with_extension("tmp");
+    image::RgbImage::new(8, 4).save_with_format(&tmp, image::ImageFormat::Png).unwrap();
+    std::fs::rename(tmp, path).unwrap();
+}
+
+#[test]
+fn native_watcher_reports_added_renamed_and_removed_images() {
+    let dir = tempfile::tempdir().unwrap();
+    let index = Arc::new(Mutex::new(ImageIndex::default()));
+    let watcher = FolderWatcher::start(WatchMode::Native, &[dir.path().to_path_buf()], ScanFilter::default(), index.clone()).unwrap();
+
+    let added = dir.path().join("new.png");
+    write_png(&added);
+    wait_for(&watcher, &LibraryChange::Added(added.clone()));
+    assert!(index.lock().unwrap().get(&added).is_some());
+
+    let renamed = dir.path().join("renamed.png");
+    std::fs::rename(&added, &renamed).unwrap();
+    wait_for(&watcher, &LibraryChange::Renamed { from: added, to: renamed.clone() });
+
+    std::fs::remove_file(&renamed).unwrap();
+    wait_for(&watcher, &LibraryChange::Removed(renamed.clone()));
+    assert!(index.lock().unwrap().is_empty());
+
+    // 移出监视的文件夹：只有改名的前一半，超时后按删除处理
+    let (outside, moved) = (tempfile::tempdir().unwrap(), dir.path().join("moved.png"));
+    write_png(&moved);
+    wait_for(&watcher, &LibraryChange::Added(moved.clone()));
+    std::fs::rename(&moved, outside.path().join("moved.png")).unwrap();
+    wait_for(&watcher, &LibraryChange::Removed(moved));
+    assert!(index.lock().unwrap().is_empty());
+}
+
+#[test]
+fn renaming_a_file_keeps_its_rotation() {
+    let dir = tempfile::tempdir().unwrap();
+    let index = Arc::new(Mutex::new(ImageIndex::default()));
+    let watcher = FolderWatcher::start(WatchMode::Native, &[dir.path().to_path_buf()], ScanFilter::default(), index.clone()).unwrap();
+
+    let original = dir.path().join("photo.png");
+    write_png(&original);
+    wait_for(&watcher, &LibraryChange::Added(original.clone()));
+    index.lock().unwrap().set_user_rotation(&original, 1);
+
+    let renamed = dir.path().join("renamed.png");
+    std::fs::rename(&original, &renamed).unwrap();
+    let expected = LibraryChange::Renamed { from: original.clone(), to: renamed.clone() };
+    let (mut changes, deadline) = (Vec::new(), Instant::now() + Duration::from_secs(10));
+    while !changes.contains(&expected) && Instant::now() < deadline {
+        changes.extend(watcher.poll());
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    // 没配对的改名要等 RENAME_TIMEOUT 才算删除
+    std::thread::sleep(RENAME_TIMEOUT * 2);
+    changes.extend(watcher.poll());
+    assert!(changes.contains(&expected));
+    // 改名不能报告成删除，否则评分、历史和播放位置都会丢掉
+    assert!(!changes.contains(&LibraryChange::Removed(original.clone())));
+    let index = index.lock().unwrap();
+    assert!(index.get(&original).is_none());
+    assert_eq!(index.get(&renamed).unwrap().user_rotation, 1);
+}
+
+#[test]
+fn playlist_applies_changes_without_reshuffling() {
+    let mut playlist = Playlist::new((0..6).map(|i| format!("/photos/{i}.jpg").into()).collect());
+    let first = playlist.next_image().unwrap();
+    let second = playlist.next_image().unwrap();
+    let order = playlist.files().to_vec();
+
+    playlist.remove(&first);
+    playlist.rename(&second, Path::new("/photos/renamed.jpg"));
+    assert!(!playlist.insert("/photos/renamed.jpg".into()));
+
+    let expected: Vec<_> = order.iter().filter(|f| **f != first).map(|f| if *f == second { "/photos/renamed.jpg".into() } else { f.clone() }).collect();
+    assert_eq!(playlist.files(), expected.as_slice());
+    // 已经显示过的图片被删除后，下一张仍然是原来排在后面的那张
+    assert_eq!(playlist.next_image().as_ref(), Some(&order[2]));
+}